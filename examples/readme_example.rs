@@ -2,7 +2,7 @@ extern crate sparse_set_container;
 use sparse_set_container::SparseSet;
 
 fn main() {
-    let mut elements = SparseSet::new();
+    let mut elements: SparseSet<&str> = SparseSet::new();
     elements.push("1");
     let key2 = elements.push("2");
     elements.push("3");
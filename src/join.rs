@@ -0,0 +1,90 @@
+// Copyright (C) Pavel Grebnev 2024-2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+use crate::SparseIndex;
+use crate::SparseKey;
+use crate::SparseSet;
+
+/// Unifies the two possible directions a join can be driven from without boxing the iterator.
+enum EitherIter<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<Item, A, B> Iterator for EitherIter<A, B>
+where
+    A: Iterator<Item = Item>,
+    B: Iterator<Item = Item>,
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        match self {
+            EitherIter::Left(iter) => iter.next(),
+            EitherIter::Right(iter) => iter.next(),
+        }
+    }
+}
+
+impl<T, I: SparseIndex> SparseSet<T, I> {
+    /// Iterates over the elements that are alive in both `self` and `other` and share the same
+    /// [`SparseKey`], yielding `(key, &self_value, &other_value)` tuples.
+    ///
+    /// This is meant for the common pattern of keeping several `SparseSet`s in sync by reusing
+    /// the same key space (e.g. parallel component storages in an ECS), where callers want to
+    /// visit only the entries present in all of them.
+    ///
+    /// Drives the walk from whichever of the two sets is smaller and probes the other one by
+    /// key, so the cost is `O(min(self.len(), other.len()))` rather than `O(self.len())`.
+    pub fn join<'a, U, J: SparseIndex>(
+        &'a self,
+        other: &'a SparseSet<U, J>,
+    ) -> impl Iterator<Item = (SparseKey, &'a T, &'a U)> {
+        if self.len() <= other.len() {
+            EitherIter::Left(
+                self.key_values()
+                    .filter_map(move |(key, t)| other.get(key).map(|u| (key, t, u))),
+            )
+        } else {
+            EitherIter::Right(
+                other
+                    .key_values()
+                    .filter_map(move |(key, u)| self.get(key).map(|t| (key, t, u))),
+            )
+        }
+    }
+
+    /// Mutably visits the elements that are alive in both `self` and `other` and share the same
+    /// [`SparseKey`], yielding `(key, &mut self_value, &mut other_value)` tuples.
+    ///
+    /// See [`Self::join`] for the matching semantics. Because the two sets are distinct
+    /// containers, handing out a mutable reference into each at once is sound; the keys are
+    /// collected up front (from whichever set is smaller) so the lookups that follow don't need
+    /// to hold a shared borrow of either set while mutable references are live.
+    pub fn join_mut<'a, U, J: SparseIndex>(
+        &'a mut self,
+        other: &'a mut SparseSet<U, J>,
+    ) -> Vec<(SparseKey, &'a mut T, &'a mut U)> {
+        let shared_keys: Vec<SparseKey> = if self.len() <= other.len() {
+            self.keys().filter(|key| other.contains(*key)).collect()
+        } else {
+            other.keys().filter(|key| self.contains(*key)).collect()
+        };
+
+        let self_ptr: *mut SparseSet<T, I> = self;
+        let other_ptr: *mut SparseSet<U, J> = other;
+
+        shared_keys
+            .into_iter()
+            .map(|key| {
+                // Safety: every key in `shared_keys` is unique (it came from iterating one
+                // set's own keys), so each loop iteration borrows a distinct dense slot in
+                // `self` and a distinct dense slot in `other`; the two sets are separate
+                // allocations, so the two mutable references can never alias each other either.
+                let self_value = unsafe { (*self_ptr).get_mut(key).expect("key is known to be alive") };
+                let other_value = unsafe { (*other_ptr).get_mut(key).expect("key is known to be alive") };
+                (key, self_value, other_value)
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,23 @@
 // Copyright (C) Pavel Grebnev 2024-2025
 // Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
 
+mod join;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod set_ops;
 mod sparse_entry;
+mod sparse_index;
 mod sparse_key;
+mod sparse_set_pair;
 mod storage;
 
+pub use sparse_index::SparseIndex;
 pub use sparse_key::SparseKey;
+pub use sparse_set_pair::SparseSetPair;
+
+use std::iter::FusedIterator;
 
 use sparse_entry::SparseEntry;
-use sparse_entry::MAX_EPOCH;
-use sparse_entry::MAX_SPARSE_INDEX;
 
 /// A container based on Sparse Set, that stores a set of items and provides a way to efficiently
 /// access them by a generated key.
@@ -18,28 +26,74 @@ use sparse_entry::MAX_SPARSE_INDEX;
 /// removals, and operations like insertions and removals have slight overhead. Also, it has higher
 /// memory consumption, since it needs to store additional data for each element.
 ///
-/// Good for cache efficiency. Doesn't require any hashing. Can't be serialized.
+/// Good for cache efficiency. Doesn't require any hashing.
+///
+/// With the `serde` feature enabled, `SparseSet<T: Serialize>`/`SparseSet<T: Deserialize>`
+/// implement `Serialize`/`Deserialize`. The serialized form captures the sparse layout (not just
+/// the live values), so deserializing reproduces storage where every previously-issued key still
+/// resolves to the same value and slot/epoch reuse continues exactly where it left off.
 ///
 /// Insertions are O(1) amortized.
 /// Removals are O(1) if the order of elements can be changed, O(n) if the order must be preserved.
 /// Accessing elements is O(1).
 ///
-/// Extra memory consumption for each value is 4 * sizeof(usize) bytes on top of the size of the
-/// value (e.g. 32 bytes per element on 64-bit systems).
-/// The memory consumption will also grow by 2 * sizeof(usize) per 2^(sizeof(usize) * 8) elements
-/// removed (e.g. 16 bytes per 18446744073709551616 elements removed on 64-bit systems).
+/// Extra memory consumption for each value is 3 * sizeof(usize) + 2 * sizeof(I) bytes on top of
+/// the size of the value (e.g. 32 bytes per element for the default `I = u32` on 64-bit systems).
+/// The memory consumption will also grow by 2 * sizeof(I) per 2^(sizeof(I) * 8) elements
+/// removed (e.g. 8 bytes per 4294967296 elements removed for the default `I = u32`).
+/// In release builds this shrinks by another sizeof(I) per sparse slot (see below).
+///
+/// The index type `I` controls the width of each sparse slot's bookkeeping (dense index,
+/// free-list link, and epoch counter); it defaults to `u32`, following Bevy-style entity
+/// indices, but can be widened to `u64`/`usize` for collections that need more than
+/// `u32::MAX` elements or removals over their lifetime. [`Self::push`] panics if an insertion
+/// would need an index or epoch beyond what `I` can represent.
+///
+/// Like Bevy's `ComponentSparseSet`, the epoch counter only exists in debug builds. A release
+/// build drops it from every sparse slot entirely and skips the generational compare on every
+/// key lookup, trusting the caller not to hold onto a key past its slot being reused — turning
+/// `get`/`contains`/etc. into a single indirection plus a liveness check. If that trust doesn't
+/// hold for your use case, stick to debug builds (or `debug-assertions = true` in a release
+/// profile) where stale keys are still reliably rejected.
+///
+/// [`Self::clear_retaining_capacity`] offers a separate, always-on way to invalidate every
+/// outstanding key at once in O(1), by bumping a generation counter the key compares against,
+/// rather than walking every slot onto the free list like [`Self::clear`] does.
+///
+/// Insertion order is preserved by default: [`Self::push`] always appends, and the
+/// order-preserving removal methods never reshuffle survivors, so [`Self::iter_in_insertion_order`]
+/// reflects true insertion order until an explicit reordering operation (a swap-based removal, a
+/// sort, [`Self::swap`]/[`Self::swap_indices`]) is used; [`Self::compact`] restores a
+/// deterministic order afterwards, handing back a remap table for any parallel data kept indexed
+/// alongside the dense array.
 ///
 /// ZST (zero-sized types) are not supported, trying to use them will result in a panic.
 #[derive(Clone)]
-pub struct SparseSet<T> {
+pub struct SparseSet<T, I: SparseIndex = u32> {
     // storage of dense and sparse values
-    storage: storage::SparseArrayStorage<T>,
+    pub(crate) storage: storage::SparseArrayStorage<T, I>,
     // a "free list" of free entries in the sparse array
-    next_free_sparse_entry: usize,
+    pub(crate) next_free_sparse_entry: usize,
+    // number of sparse slots permanently retired after exhausting their epoch counter
+    pub(crate) retired_slot_count: usize,
+    // the largest sparse length this set has ever reached; unlike the storage's own sparse
+    // length, this never comes back down when `shrink_to`/`shrink_to_fit` reclaims a trailing
+    // run of dead slots, so a key whose slot was reclaimed that way can still be told apart from
+    // one that never belonged to this set at all (see `resolve_sparse_entry`)
+    high_water_mark: usize,
+    // bumped by `clear_retaining_capacity`; every key remembers the generation it was issued in,
+    // so a single compare against this instantly rejects every key from before the last clear
+    generation: usize,
+    // how many leading sparse slots belong to the current generation, i.e. have either been
+    // handed out or pushed onto the free list since the last `clear_retaining_capacity` (or since
+    // the set was created, if that never happened); the rest of the physical sparse array, if any,
+    // is leftover capacity from a previous generation that `push`/`push_front` can claim directly,
+    // without the O(capacity) work of walking it onto the free list first
+    frontier: usize,
 }
 
 #[allow(dead_code)]
-impl<T> SparseSet<T> {
+impl<T, I: SparseIndex> SparseSet<T, I> {
     /// Creates a new SparseSet. Does not allocate.
     ///
     /// # Panics
@@ -49,7 +103,11 @@ impl<T> SparseSet<T> {
         assert!(size_of::<T>() > 0, "Zero-sized types are not supported");
         Self {
             storage: storage::SparseArrayStorage::new(),
-            next_free_sparse_entry: MAX_SPARSE_INDEX,
+            next_free_sparse_entry: I::MAX_SPARSE_INDEX,
+            retired_slot_count: 0,
+            high_water_mark: 0,
+            generation: 0,
+            frontier: 0,
         }
     }
 
@@ -63,7 +121,11 @@ impl<T> SparseSet<T> {
         assert!(size_of::<T>() > 0, "Zero-sized types are not supported");
         Self {
             storage: storage::SparseArrayStorage::with_capacity(capacity),
-            next_free_sparse_entry: MAX_SPARSE_INDEX,
+            next_free_sparse_entry: I::MAX_SPARSE_INDEX,
+            retired_slot_count: 0,
+            high_water_mark: 0,
+            generation: 0,
+            frontier: 0,
         }
     }
 
@@ -77,26 +139,115 @@ impl<T> SparseSet<T> {
     ///
     /// # Panics
     ///
-    /// Panics if a memory allocation fails.
+    /// Panics if a memory allocation fails, or if the insertion would need a sparse index or epoch beyond what this set's index type `I` can represent.
     pub fn push(&mut self, value: T) -> SparseKey {
-        // if there are free entries in the sparse array, use one of them
-        if self.next_free_sparse_entry != MAX_SPARSE_INDEX {
+        if let Some((new_sparse_index, epoch)) = self.reserve_existing_sparse_slot() {
+            let key = SparseKey::new(new_sparse_index, epoch, self.generation);
+
+            self.storage
+                .insert_with_existing_sparse_item(self.storage.get_dense_len(), key, value);
+
+            key
+        } else {
+            // extend the sparse array
+            let key = self.storage.insert_with_new_sparse_item(
+                self.storage.get_dense_len(),
+                value,
+                self.generation,
+            );
+            self.frontier += 1;
+            key
+        }
+    }
+
+    /// Hands out a sparse index (and the epoch a key for it should start at) that the sparse
+    /// array already has room for, without growing it: either the head of the free list, or, if
+    /// that's empty, the next untouched slot below `frontier` left over from before the last
+    /// [`Self::clear_retaining_capacity`]. Returns `None` if neither is available and the caller
+    /// needs to grow the sparse array instead.
+    fn reserve_existing_sparse_slot(&mut self) -> Option<(usize, usize)> {
+        if self.next_free_sparse_entry != I::MAX_SPARSE_INDEX {
             let new_sparse_index = self.next_free_sparse_entry;
             let free_sparse_entry = self.storage.get_sparse()[new_sparse_index];
             self.next_free_sparse_entry = free_sparse_entry.next_free();
+            Some((new_sparse_index, free_sparse_entry.reused_epoch()))
+        } else if self.frontier < self.storage.get_sparse_len() {
+            let new_sparse_index = self.frontier;
+            self.frontier += 1;
+            Some((new_sparse_index, 0))
+        } else {
+            None
+        }
+    }
 
-            let key = SparseKey {
-                sparse_index: new_sparse_index,
-                epoch: free_sparse_entry.next_epoch(),
-            };
+    /// Inserts a new value into the set, like [`Self::push`], but also returns the dense index it
+    /// landed at.
+    ///
+    /// Following indexmap's `insert_full`, this saves callers who need both the key and the dense
+    /// position from having to follow up with [`Self::index`].
+    ///
+    /// O(1) amortized time complexity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a memory allocation fails, or if the insertion would need a sparse index or epoch beyond what this set's index type `I` can represent.
+    pub fn push_full(&mut self, value: T) -> (usize, SparseKey) {
+        let index = self.storage.get_dense_len();
+        let key = self.push(value);
+        (index, key)
+    }
+
+    /// Inserts a new value at the front of the set and returns a key that can be used to access
+    /// it.
+    ///
+    /// Unlike [`Self::push`], this shifts every existing element one slot to the right, so it's
+    /// O(n) rather than amortized O(1); prefer `push` unless front-biased insertion order
+    /// actually matters for your use case.
+    ///
+    /// This is deliberately not a ring-buffer-backed deque: every sparse entry stores the
+    /// *physical* dense index of the element it points at, and that physical index is read
+    /// directly off [`Self::get`]/[`Self::swap_remove`]/[`Self::remove`]/the iterators/the
+    /// set-algebra helpers and more, all of which assume `get_dense_values()`/`get_dense_keys()`
+    /// are plain contiguous slices. Making `push_front` O(1) by wrapping the dense region would
+    /// mean either reindexing every live entry whenever the logical front moves (no cheaper than
+    /// the shift below) or rewriting all of those call sites to translate through a wrap-aware
+    /// head/tail, which is a much larger change than this method. If that tradeoff is worth it
+    /// for your use case, a `VecDeque` alongside a plain `SparseSet` is likely a better fit today.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a memory allocation fails, or if the insertion would need a sparse index or epoch beyond what this set's index type `I` can represent.
+    pub fn push_front(&mut self, value: T) -> SparseKey {
+        let key = if let Some((new_sparse_index, epoch)) = self.reserve_existing_sparse_slot() {
+            let key = SparseKey::new(new_sparse_index, epoch, self.generation);
 
-            self.storage.add_with_existing_sparse_item(key, value);
+            self.storage.insert_with_existing_sparse_item(0, key, value);
 
             key
         } else {
-            // extend the sparse array
-            self.storage.add_with_new_sparse_item(value)
+            let key = self
+                .storage
+                .insert_with_new_sparse_item(0, value, self.generation);
+            self.frontier += 1;
+            key
+        };
+
+        // every element that used to occupy the dense array got shifted one slot to the right
+        // to make room at the front; re-point their sparse entries at their new dense position
+        for i in 1..self.storage.get_dense_len() {
+            self.project_dense_key_to_sparse(i);
         }
+
+        key
+    }
+
+    /// Removes the element at the front of the set, keeping the order of the remaining elements.
+    /// Returns the removed value if the set was not empty.
+    ///
+    /// O(n) time complexity, same as [`Self::remove`].
+    pub fn pop_front(&mut self) -> Option<T> {
+        let front_key = self.storage.get_dense_keys().first().copied()?;
+        self.remove(front_key)
     }
 
     /// Removes an element from the set using the key, swapping it with the last element.
@@ -108,14 +259,12 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn swap_remove(&mut self, key: SparseKey) -> Option<T> {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key.sparse_index < self.storage.get_sparse_len());
-
-        let sparse_entry = self.storage.get_sparse_mut()[key.sparse_index];
-        if sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch {
+        let Some(sparse_entry) = self.resolve_sparse_entry(key) else {
+            return None;
+        };
+        if sparse_entry.is_alive_for(key) {
             let swapped_sparse_index =
-                self.storage.get_dense_keys()[self.storage.get_dense_len() - 1].sparse_index;
+                self.storage.get_dense_keys()[self.storage.get_dense_len() - 1].sparse_index();
             self.storage.get_sparse_mut()[swapped_sparse_index]
                 .set_dense_index(sparse_entry.dense_index());
 
@@ -138,14 +287,12 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn remove(&mut self, key: SparseKey) -> Option<T> {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key.sparse_index < self.storage.get_sparse_len());
-
-        let sparse_entry = self.storage.get_sparse()[key.sparse_index];
-        if sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch {
+        let Some(sparse_entry) = self.resolve_sparse_entry(key) else {
+            return None;
+        };
+        if sparse_entry.is_alive_for(key) {
             for i in sparse_entry.dense_index() + 1..self.storage.get_dense_len() {
-                let sparse_index = self.storage.get_dense_keys()[i].sparse_index;
+                let sparse_index = self.storage.get_dense_keys()[i].sparse_index();
 
                 self.storage.get_sparse_mut()[sparse_index].dense_index_move_left();
             }
@@ -161,6 +308,10 @@ impl<T> SparseSet<T> {
     }
 
     /// Remove all the elements from the set.
+    ///
+    /// O(n) time complexity, since every removed element's sparse slot is individually walked
+    /// onto the free list. For clearing a set that's about to be reused, see
+    /// [`Self::clear_retaining_capacity`], which does the same in O(1).
     pub fn clear(&mut self) {
         for i in 0..self.storage.get_dense_len() {
             self.mark_as_free(self.storage.get_dense_keys()[i]);
@@ -168,6 +319,239 @@ impl<T> SparseSet<T> {
         self.storage.clear_dense();
     }
 
+    /// Remove all the elements from the set in O(1), keeping the underlying capacity.
+    ///
+    /// Unlike [`Self::clear`], this doesn't walk the sparse array to rebuild the free list: it
+    /// just drops the dense values and bumps the set's generation counter, which instantly
+    /// invalidates every key issued before the call (they carry the old generation, so
+    /// [`Self::get`]/[`Self::remove`]/etc. reject them exactly as if the elements had been
+    /// individually removed). The trick is the same one regex-automata uses to clear its sparse
+    /// sets in constant time.
+    ///
+    /// Capacity is retained: the next pushes reclaim the old sparse slots directly (see
+    /// `frontier` in the struct docs) rather than reallocating, so this is the right choice for a
+    /// set that gets cleared and refilled repeatedly, where [`Self::clear`]'s O(n) free-list walk
+    /// would otherwise be redone every time.
+    pub fn clear_retaining_capacity(&mut self) {
+        self.storage.clear_dense();
+        self.next_free_sparse_entry = I::MAX_SPARSE_INDEX;
+        self.frontier = 0;
+        self.retired_slot_count = 0;
+        self.generation += 1;
+    }
+
+    /// Removes every element from the set, returning an iterator that yields each removed
+    /// element's key paired with its owned value.
+    ///
+    /// Equivalent to `clear()`, except the contents are handed back instead of dropped. Elements
+    /// are yielded in reverse dense order (each step swap-removes the last dense element, which is
+    /// cheaper than shifting the whole array down); collect and reverse if forward dense order is
+    /// needed. If the returned iterator is dropped before being fully consumed, the remaining
+    /// elements are still removed and their sparse slots freed, so the set is always empty once
+    /// the iterator is gone, matching `clear()`.
+    pub fn drain(&mut self) -> impl Iterator<Item = (SparseKey, T)> + '_ {
+        Drain { set: self }
+    }
+
+    /// Retains only the elements for which `predicate` returns `true`, removing the rest.
+    ///
+    /// Visits each element once in a single compaction pass, so this is considerably cheaper for
+    /// mass removal than calling [`Self::remove`] once per element to remove. The relative order
+    /// of the surviving elements **is preserved**, and their keys stay alive at the same epoch;
+    /// only the keys of removed elements are invalidated. Mirrors the `retain` found on
+    /// `HashSet`/`IndexSet`, but order-preserving (like [`Self::remove`]) rather than swap-based.
+    ///
+    /// O(n) time complexity.
+    pub fn retain<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(SparseKey, &mut T) -> bool,
+    {
+        let len = self.storage.get_dense_len();
+        let mut write = 0;
+        for read in 0..len {
+            let key = self.storage.get_dense_keys()[read];
+            let keep = predicate(key, &mut self.storage.get_dense_values_mut()[read]);
+            if keep {
+                if write != read {
+                    self.storage.get_dense_values_mut().swap(write, read);
+                    self.storage.get_dense_keys_mut().swap(write, read);
+                }
+                write += 1;
+            } else {
+                self.mark_as_free(key);
+            }
+        }
+
+        self.storage.truncate_dense(write);
+
+        for i in 0..write {
+            self.project_dense_key_to_sparse(i);
+        }
+    }
+
+    /// Removes and returns every element for which `predicate` returns `true`, as `(key, value)`
+    /// pairs.
+    ///
+    /// Collects the removed pairs eagerly into a `Vec` rather than returning a lazy iterator,
+    /// since driving removal incrementally would require re-borrowing the set on every call to
+    /// `next()`. Unlike [`Self::retain`], extraction is swap-based, so **the relative order of
+    /// the elements left behind is not preserved**.
+    ///
+    /// For a lazy version that can be stopped early without removing elements it hasn't visited
+    /// yet, see [`Self::drain_filter`].
+    ///
+    /// O(n) time complexity.
+    pub fn extract_if<F>(&mut self, mut predicate: F) -> Vec<(SparseKey, T)>
+    where
+        F: FnMut(SparseKey, &mut T) -> bool,
+    {
+        let mut extracted = Vec::new();
+        let mut index = 0;
+        while index < self.storage.get_dense_len() {
+            let key = self.storage.get_dense_keys()[index];
+            let matches = predicate(key, &mut self.storage.get_dense_values_mut()[index]);
+            if matches {
+                let value = self
+                    .swap_remove(key)
+                    .expect("key was just read from the dense array");
+                extracted.push((key, value));
+            } else {
+                index += 1;
+            }
+        }
+        extracted
+    }
+
+    /// Returns a lazy iterator that removes and yields each element for which `predicate`
+    /// returns `true`, as `(key, value)` pairs.
+    ///
+    /// Each call to `next()` walks forward from where the previous call left off, testing and
+    /// (on a match) swap-removing one element, so the whole walk stays O(n) overall rather than
+    /// O(n · remove-cost). Like [`Self::extract_if`], removal is swap-based, so **the relative
+    /// order of the elements left behind is not preserved**, but every surviving element keeps
+    /// its key valid at the same epoch.
+    ///
+    /// Unlike [`Self::drain`], dropping this iterator before it's exhausted does **not** remove
+    /// the remaining elements: only elements the predicate has actually been asked about (and
+    /// decided to keep or remove) are ever touched, so stopping early leaves the rest of the set
+    /// untouched and their keys unaffected.
+    ///
+    /// O(n) time complexity overall.
+    pub fn drain_filter<'a, F>(
+        &'a mut self,
+        predicate: F,
+    ) -> impl Iterator<Item = (SparseKey, T)> + 'a
+    where
+        F: FnMut(SparseKey, &mut T) -> bool + 'a,
+    {
+        DrainFilter {
+            set: self,
+            index: 0,
+            predicate,
+        }
+    }
+
+    /// Returns the number of elements the set can hold without reallocating.
+    ///
+    /// This tracks the sparse array's capacity rather than the dense one, since sparse slots
+    /// belonging to removed-but-not-yet-reclaimed elements still count against it; see
+    /// [`Self::shrink_to_fit`].
+    pub fn capacity(&self) -> usize {
+        self.storage.get_sparse_capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted.
+    ///
+    /// A no-op if the existing capacity already covers it.
+    pub fn reserve(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+
+    /// Reserves capacity for exactly `additional` more elements to be inserted.
+    ///
+    /// Identical to [`Self::reserve`], which itself allocates no more than asked for (growth by
+    /// doubling only happens when a push runs out of capacity). Provided under this name too for
+    /// parity with the standard collections' `reserve`/`reserve_exact` pair.
+    ///
+    /// A no-op if the existing capacity already covers it.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the set as much as possible.
+    ///
+    /// Sparse slots that belong to already-removed elements can't be reclaimed without
+    /// renumbering the slots after them, which would invalidate outstanding keys. But a
+    /// *trailing* run of removed slots (with no live slot after them) has nothing left to
+    /// renumber, so it's dropped first; the resulting capacity comes down to the number of live
+    /// and previously-occupied-but-not-trailing sparse slots, which is `len()` in the common case
+    /// where the most recently removed elements were also the most recently inserted ones.
+    pub fn shrink_to_fit(&mut self) {
+        self.trim_trailing_free_slots();
+        self.storage.shrink_to(0);
+    }
+
+    /// Shrinks the capacity of the set with a lower bound.
+    ///
+    /// The capacity will remain at least as large as both `min_capacity` and what
+    /// [`Self::shrink_to_fit`] would leave it at, whichever is larger.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.trim_trailing_free_slots();
+        self.storage.shrink_to(min_capacity);
+    }
+
+    /// Drops the trailing run of free sparse slots (if any), unlinking them from the free list
+    /// and shrinking the sparse length past them. Slots before the trailing run are left alone,
+    /// since reclaiming them would require renumbering (and thus invalidating) every live slot
+    /// after them. Slots at or past `frontier` are trimmed unconditionally: they're leftover
+    /// capacity from before the last [`Self::clear_retaining_capacity`], never linked into the
+    /// free list, so their stale alive/free bit pattern can't be trusted either way.
+    fn trim_trailing_free_slots(&mut self) {
+        let sparse_len = self.storage.get_sparse_len();
+        // record the pre-trim length as a high-water mark, so a key whose slot gets reclaimed
+        // below can still be recognized as "stale", rather than mistaken for one that was never
+        // valid for this set at all (see `resolve_sparse_entry`)
+        self.high_water_mark = self.high_water_mark.max(sparse_len);
+
+        // retired slots are dead but off the free list, and stay allocated so their index is never
+        // handed out again; only slots on the free list count as free here
+        let mut on_free_list = vec![false; sparse_len];
+        let mut cursor = self.next_free_sparse_entry;
+        while cursor != I::MAX_SPARSE_INDEX {
+            on_free_list[cursor] = true;
+            cursor = self.storage.get_sparse()[cursor].next_free();
+        }
+
+        let mut new_len = sparse_len;
+        while new_len > 0 && (new_len > self.frontier || on_free_list[new_len - 1]) {
+            new_len -= 1;
+        }
+        if new_len == sparse_len {
+            return;
+        }
+        self.frontier = self.frontier.min(new_len);
+
+        // rebuild the free list with the trimmed indices removed, preserving the relative order
+        // of the links that remain
+        let mut remaining_free = Vec::new();
+        let mut cursor = self.next_free_sparse_entry;
+        while cursor != I::MAX_SPARSE_INDEX {
+            let next = self.storage.get_sparse()[cursor].next_free();
+            if cursor < new_len {
+                remaining_free.push(cursor);
+            }
+            cursor = next;
+        }
+
+        self.next_free_sparse_entry = I::MAX_SPARSE_INDEX;
+        for &index in remaining_free.iter().rev() {
+            self.storage.get_sparse_mut()[index].set_next_free(self.next_free_sparse_entry);
+            self.next_free_sparse_entry = index;
+        }
+
+        self.storage.truncate_sparse(new_len);
+    }
+
     /// Swaps two elements in the set using their keys.
     ///
     /// O(1) time complexity.
@@ -177,15 +561,12 @@ impl<T> SparseSet<T> {
     /// - Panics if any of the keys are not present in the set (were removed)
     /// - Can panic if the used keys are not from this SparseSet.
     pub fn swap(&mut self, key1: SparseKey, key2: SparseKey) {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key1.sparse_index < self.storage.get_sparse_len());
-        assert!(key2.sparse_index < self.storage.get_sparse_len());
+        let entries = match (self.resolve_sparse_entry(key1), self.resolve_sparse_entry(key2)) {
+            (Some(e1), Some(e2)) if e1.is_alive() && e2.is_alive() => Some((e1, e2)),
+            _ => None,
+        };
 
-        let sparse_entry1 = self.storage.get_sparse()[key1.sparse_index];
-        let sparse_entry2 = self.storage.get_sparse()[key2.sparse_index];
-
-        if sparse_entry1.is_alive() && sparse_entry2.is_alive() {
+        if let Some((sparse_entry1, sparse_entry2)) = entries {
             self.storage
                 .get_dense_values_mut()
                 .swap(sparse_entry1.dense_index(), sparse_entry2.dense_index());
@@ -195,10 +576,10 @@ impl<T> SparseSet<T> {
 
             // swap the references in the sparse array
             let sparse_array = self.storage.get_sparse_mut();
-            sparse_array[key1.sparse_index] =
-                SparseEntry::new_alive(sparse_entry2.dense_index(), sparse_entry1.epoch());
-            sparse_array[key2.sparse_index] =
-                SparseEntry::new_alive(sparse_entry1.dense_index(), sparse_entry2.epoch());
+            sparse_array[key1.sparse_index()] =
+                SparseEntry::new_alive(sparse_entry2.dense_index(), sparse_entry1.alive_epoch());
+            sparse_array[key2.sparse_index()] =
+                SparseEntry::new_alive(sparse_entry1.dense_index(), sparse_entry2.alive_epoch());
         } else {
             panic!("Cannot swap elements that are not alive");
         }
@@ -256,6 +637,158 @@ impl<T> SparseSet<T> {
         }
     }
 
+    /// Swaps the elements at the given dense indices, fixing up the sparse back-pointers so
+    /// outstanding keys still resolve to the same element afterwards.
+    ///
+    /// O(1) time complexity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        self.storage.get_dense_values_mut().swap(a, b);
+        self.storage.get_dense_keys_mut().swap(a, b);
+        self.project_dense_key_to_sparse(a);
+        self.project_dense_key_to_sparse(b);
+    }
+
+    /// Sorts the dense values in ascending order, keeping every existing key pointing at the
+    /// same logical element.
+    ///
+    /// O(n log n) time complexity, stable sort.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(|a, b| a.cmp(b));
+    }
+
+    /// Sorts the dense values with the given comparator, keeping every existing key pointing at
+    /// the same logical element.
+    ///
+    /// O(n log n) time complexity, stable sort.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        let dense_values = self.storage.get_dense_values();
+        let mut indices: Vec<usize> = (0..dense_values.len()).collect();
+        indices.sort_by(|&a, &b| compare(&dense_values[a], &dense_values[b]));
+        self.apply_dense_permutation(indices);
+    }
+
+    /// Sorts the dense values with the given comparator, keeping every existing key pointing at
+    /// the same logical element.
+    ///
+    /// O(n log n) time complexity, not guaranteed to be stable, may perform faster than
+    /// [`Self::sort_by`].
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        let dense_values = self.storage.get_dense_values();
+        let mut indices: Vec<usize> = (0..dense_values.len()).collect();
+        indices.sort_unstable_by(|&a, &b| compare(&dense_values[a], &dense_values[b]));
+        self.apply_dense_permutation(indices);
+    }
+
+    /// Sorts the dense values by the key the given function extracts from each value, keeping
+    /// every existing key pointing at the same logical element.
+    ///
+    /// O(n log n) time complexity, stable sort.
+    pub fn sort_by_key<K, F>(&mut self, mut extract_key: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| extract_key(a).cmp(&extract_key(b)));
+    }
+
+    /// Sorts the dense values by the key the given function extracts from each value, keeping
+    /// every existing key pointing at the same logical element.
+    ///
+    /// O(n log n) time complexity, not guaranteed to be stable, may perform faster than
+    /// [`Self::sort_by_key`].
+    pub fn sort_unstable_by_key<K, F>(&mut self, mut extract_key: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_unstable_by(|a, b| extract_key(a).cmp(&extract_key(b)));
+    }
+
+    /// Sorts the dense values by their [`SparseKey`]'s `(sparse_index, epoch)`, keeping every
+    /// existing key pointing at the same logical element.
+    ///
+    /// This gives a deterministic, key-derived ordering independent of the values themselves,
+    /// which is useful for restoring a canonical layout after `sort_by`/`sort_unstable_by` or
+    /// other reordering operations have scrambled the dense array. Identical to [`Self::compact`],
+    /// except it discards the remap table that callers without a parallel column to fix up don't
+    /// need.
+    ///
+    /// O(n log n) time complexity, stable sort.
+    pub fn sort_keys(&mut self) {
+        self.compact();
+    }
+
+    /// Restores a deterministic dense layout ordered by each element's `(sparse_index, epoch)`,
+    /// undoing any scrambling left behind by swap-based operations ([`Self::swap_remove`],
+    /// [`Self::extract_if`], [`Self::drain`], [`Self::swap`], [`Self::swap_indices`]) or explicit
+    /// sorts, and returns the remap from each surviving element's old dense index to its new one.
+    ///
+    /// This is deterministic and stable across calls, but it is *not* true creation order once a
+    /// sparse slot has been reused: a slot's `sparse_index` reflects which slot it lives in, not
+    /// when it was pushed, so an element that reuses a low, long-freed slot sorts as if it were
+    /// created early even though it's actually the newest. If only order-preserving operations
+    /// have been used so far, see [`Self::iter_in_insertion_order`] for true insertion order
+    /// without needing to call this at all.
+    ///
+    /// Every removal in this set already defragments the dense array immediately (there are no
+    /// tombstones to reclaim), so this never changes `len()` or frees any memory; what it buys
+    /// you is a deterministic order plus the index remap needed to fix up a parallel column
+    /// (e.g. a `Vec<U>` you're keeping indexed by dense position alongside this set) after
+    /// reordering it.
+    ///
+    /// O(n log n) time complexity, stable sort.
+    pub fn compact(&mut self) -> Vec<usize> {
+        let dense_keys = self.storage.get_dense_keys();
+        let mut indices: Vec<usize> = (0..dense_keys.len()).collect();
+        indices.sort_by_key(|&i| (dense_keys[i].sparse_index(), dense_keys[i].epoch));
+        self.apply_dense_permutation(indices)
+    }
+
+    /// Reorders the dense values and keys according to `indices` (`indices[i]` is the dense
+    /// index, before reordering, of the element that should end up at position `i`), then
+    /// repairs the sparse table so every live key keeps resolving to the same element.
+    /// Applies a permutation to the dense array, returning the remap from each element's old
+    /// dense index to its new one (the same shape as [`Self::compact`]'s return value).
+    fn apply_dense_permutation(&mut self, indices: Vec<usize>) -> Vec<usize> {
+        // `indices` tells us, for each destination slot, which slot the element should come
+        // from. The cycle-following swap loop below instead needs the inverse: for each
+        // *source* slot, which destination it should move to. Invert it up front so the loop
+        // can walk cycles by repeatedly swapping an element into its final resting place.
+        let mut destination_of = vec![0usize; indices.len()];
+        for (dest, &source) in indices.iter().enumerate() {
+            destination_of[source] = dest;
+        }
+        let remap = destination_of.clone();
+
+        for i in 0..destination_of.len() {
+            while destination_of[i] != i {
+                let target = destination_of[i];
+                self.storage.get_dense_values_mut().swap(i, target);
+                self.storage.get_dense_keys_mut().swap(i, target);
+                destination_of.swap(i, target);
+            }
+        }
+
+        for i in 0..self.storage.get_dense_len() {
+            self.project_dense_key_to_sparse(i);
+        }
+
+        remap
+    }
+
     /// Returns a reference to the value stored at the given key.
     /// If the key is not valid, returns None.
     ///
@@ -265,12 +798,8 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn get(&self, key: SparseKey) -> Option<&T> {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key.sparse_index < self.storage.get_sparse_len());
-
-        let sparse_entry = self.storage.get_sparse()[key.sparse_index];
-        if sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch {
+        let sparse_entry = self.resolve_sparse_entry(key)?;
+        if sparse_entry.is_alive_for(key) {
             Some(&self.storage.get_dense_values()[sparse_entry.dense_index()])
         } else {
             // either there's no element, or there's a newer element the value points to
@@ -287,13 +816,9 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn get_mut(&mut self, key: SparseKey) -> Option<&mut T> {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key.sparse_index < self.storage.get_sparse_len());
+        let sparse_entry = self.resolve_sparse_entry(key)?;
 
-        let sparse_entry = self.storage.get_sparse()[key.sparse_index];
-
-        if sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch {
+        if sparse_entry.is_alive_for(key) {
             Some(&mut self.storage.get_dense_values_mut()[sparse_entry.dense_index()])
         } else {
             // either there's no element, or there's a newer element the value points to
@@ -301,6 +826,87 @@ impl<T> SparseSet<T> {
         }
     }
 
+    /// Returns the dense index and a reference to the value stored at the given key, like
+    /// [`Self::get`], but without a separate call to [`Self::index`].
+    /// If the key is not valid, returns None.
+    ///
+    /// O(1) time complexity.
+    ///
+    /// # Panics
+    ///
+    /// Can panic if the used key is not from this SparseSet.
+    pub fn get_full(&self, key: SparseKey) -> Option<(usize, &T)> {
+        let sparse_entry = self.resolve_sparse_entry(key)?;
+        if sparse_entry.is_alive_for(key) {
+            let dense_index = sparse_entry.dense_index();
+            Some((dense_index, &self.storage.get_dense_values()[dense_index]))
+        } else {
+            // either there's no element, or there's a newer element the value points to
+            None
+        }
+    }
+
+    /// Returns the dense index and a mutable reference to the value stored at the given key, like
+    /// [`Self::get_mut`], but without a separate call to [`Self::index`].
+    /// If the key is not valid, returns None.
+    ///
+    /// O(1) time complexity.
+    ///
+    /// # Panics
+    ///
+    /// Can panic if the used key is not from this SparseSet.
+    pub fn get_full_mut(&mut self, key: SparseKey) -> Option<(usize, &mut T)> {
+        let sparse_entry = self.resolve_sparse_entry(key)?;
+        if sparse_entry.is_alive_for(key) {
+            let dense_index = sparse_entry.dense_index();
+            Some((dense_index, &mut self.storage.get_dense_values_mut()[dense_index]))
+        } else {
+            // either there's no element, or there's a newer element the value points to
+            None
+        }
+    }
+
+    /// Returns mutable references to the elements at several keys at once.
+    ///
+    /// Returns `None` if any key is dead/stale, or if two of the given keys resolve to the same
+    /// element (handing out two mutable references to one element would violate aliasing rules).
+    ///
+    /// O(N^2) time complexity in the number of keys requested (each pair is checked for
+    /// aliasing), O(1) in the size of the set.
+    ///
+    /// # Panics
+    ///
+    /// Can panic if one of the used keys is not from this SparseSet.
+    pub fn get_disjoint_mut<const N: usize>(&mut self, keys: [SparseKey; N]) -> Option<[&mut T; N]> {
+        let mut dense_indices = [0usize; N];
+        for i in 0..N {
+            let key = keys[i];
+            let Some(sparse_entry) = self.resolve_sparse_entry(key) else {
+                return None;
+            };
+            if !sparse_entry.is_alive_for(key) {
+                return None;
+            }
+            dense_indices[i] = sparse_entry.dense_index();
+        }
+
+        for i in 0..N {
+            for j in (i + 1)..N {
+                if dense_indices[i] == dense_indices[j] {
+                    return None;
+                }
+            }
+        }
+
+        let dense_values_ptr = self.storage.get_dense_values_mut().as_mut_ptr();
+        // Safety: all indices in `dense_indices` were just checked to be distinct and in bounds
+        // (each came from a live sparse entry), so each `add` below yields a pointer to a
+        // different, valid element, and handing out one `&mut` per element is sound.
+        Some(std::array::from_fn(|i| unsafe {
+            &mut *dense_values_ptr.add(dense_indices[i])
+        }))
+    }
+
     /// Returns true if the key points to a valid element in the set.
     ///
     /// O(1) time complexity.
@@ -309,13 +915,10 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn contains(&self, key: SparseKey) -> bool {
-        if key.sparse_index >= self.storage.get_sparse_len() {
-            debug_assert!(false, "The key is not valid for this SparseSet");
-            return false;
+        match self.resolve_sparse_entry(key) {
+            Some(sparse_entry) => sparse_entry.is_alive_for(key),
+            None => false,
         }
-
-        let sparse_entry = self.storage.get_sparse()[key.sparse_index];
-        sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch
     }
 
     /// Returns the number of elements in the set.
@@ -332,18 +935,66 @@ impl<T> SparseSet<T> {
         self.storage.get_dense_values().is_empty()
     }
 
+    /// Returns the number of sparse slots that have been permanently retired after exhausting
+    /// their epoch counter.
+    ///
+    /// Retired slots are never reused, which guarantees a key can never end up aliasing a
+    /// different logical element no matter how many times its slot is inserted into and removed
+    /// from. They stay allocated forever, so [`Self::shrink_to_fit`] cannot reclaim them without
+    /// renumbering live slots.
+    ///
+    /// O(1) time complexity.
+    pub fn retired_slot_count(&self) -> usize {
+        self.retired_slot_count
+    }
+
+    /// Reclaims every permanently retired slot, making its sparse index eligible for reuse
+    /// again.
+    ///
+    /// This is only safe when nothing in the set can still be confused with a slot's previous
+    /// occupant, which is exactly what an empty set guarantees: bumping the generation counter
+    /// (the same mechanism [`Self::clear_retaining_capacity`] uses) instantly invalidates every
+    /// outstanding key, retired slots included, so reusing their index afterwards can't alias a
+    /// stale handle. Unlike [`Self::shrink_to_fit`], which can never get this capacity back once
+    /// a slot retires, this recovers it in O(1) at the cost of invalidating every key — which
+    /// costs nothing extra on an already-empty set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set is not empty.
+    pub fn reclaim_retired(&mut self) {
+        assert!(
+            self.is_empty(),
+            "reclaim_retired can only be called on an empty SparseSet"
+        );
+        self.clear_retaining_capacity();
+    }
+
     /// Returns an iterator over the values of the set.
-    pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> {
+    ///
+    /// The dense storage is contiguous, so the iterator is double-ended, reports an exact
+    /// `len()`, and is fused.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator + FusedIterator {
         self.storage.get_dense_values().iter()
     }
 
     /// Returns an iterator over the mutable values of the set.
-    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> {
+    ///
+    /// The dense storage is contiguous, so the iterator is double-ended, reports an exact
+    /// `len()`, and is fused.
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator + FusedIterator {
         self.storage.get_dense_values_mut().iter_mut()
     }
 
     /// Returns an iterator over the keys of the set.
-    pub fn keys(&self) -> impl DoubleEndedIterator<Item = SparseKey> + '_ {
+    ///
+    /// The dense storage is contiguous, so the iterator is double-ended, reports an exact
+    /// `len()`, and is fused.
+    pub fn keys(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = SparseKey> + ExactSizeIterator + FusedIterator + '_ {
         self.storage.get_dense_keys().iter().copied()
     }
 
@@ -352,6 +1003,23 @@ impl<T> SparseSet<T> {
         self.storage.get_dense_keys().get(index).copied()
     }
 
+    /// Returns a reference to the value stored at the given dense index.
+    ///
+    /// This is the position `values()` would yield it at, not a [`SparseKey`]; use
+    /// [`Self::get_key`] to recover the stable key for a dense position.
+    ///
+    /// O(1) time complexity.
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.storage.get_dense_values().get(index)
+    }
+
+    /// Returns a mutable reference to the value stored at the given dense index.
+    ///
+    /// O(1) time complexity.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.storage.get_dense_values_mut().get_mut(index)
+    }
+
     /// Returns the index of an element with the given key.
     /// If the key is not valid, returns None.
     ///
@@ -361,12 +1029,8 @@ impl<T> SparseSet<T> {
     ///
     /// Can panic if the used key is not from this SparseSet.
     pub fn index(&self, key: SparseKey) -> Option<usize> {
-        // this can happen only if the key is from another SparseSet
-        // in this case nothing is guaranteed anymore, we should panic
-        assert!(key.sparse_index < self.storage.get_sparse_len());
-
-        let sparse_entry = self.storage.get_sparse()[key.sparse_index];
-        if sparse_entry.is_alive() && sparse_entry.epoch() == key.epoch {
+        let sparse_entry = self.resolve_sparse_entry(key)?;
+        if sparse_entry.is_alive_for(key) {
             Some(sparse_entry.dense_index())
         } else {
             // either there's no element, or there's a newer element the value points to
@@ -374,11 +1038,27 @@ impl<T> SparseSet<T> {
         }
     }
 
+    /// Alias for [`Self::index`], kept for callers used to indexmap's `get_index_of` naming.
+    ///
+    /// O(1) time complexity.
+    ///
+    /// # Panics
+    ///
+    /// Can panic if the used key is not from this SparseSet.
+    pub fn index_of(&self, key: SparseKey) -> Option<usize> {
+        self.index(key)
+    }
+
     /// Returns an iterator over the key-value pairs of the set.
     ///
     /// Note that if you intend to iterate over key-values in time-critical code, it may be better
     /// to instead store the keys in the elements themselves to reduce CPU cache misses.
-    pub fn key_values(&self) -> impl DoubleEndedIterator<Item = (SparseKey, &T)> {
+    ///
+    /// The dense storage is contiguous, so the iterator is double-ended, reports an exact
+    /// `len()`, and is fused.
+    pub fn key_values(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (SparseKey, &T)> + ExactSizeIterator + FusedIterator {
         self.storage
             .get_dense_keys()
             .iter()
@@ -386,27 +1066,188 @@ impl<T> SparseSet<T> {
             .zip(self.storage.get_dense_values().iter())
     }
 
-    fn mark_as_free(&mut self, key: SparseKey) {
-        self.storage.get_sparse_mut()[key.sparse_index].mark_free(self.next_free_sparse_entry);
+    /// Alias for [`Self::key_values`], kept for callers used to regex-automata's sparse-set
+    /// naming.
+    ///
+    /// [`Self::push`]/[`Self::push_full`] always append, and [`Self::remove`], [`Self::retain`]
+    /// and [`Self::pop_front`] preserve the relative order of the elements they leave behind, so
+    /// this reflects true insertion order as long as only those operations have been used.
+    /// Operations that explicitly reorder the dense array ([`Self::swap_remove`],
+    /// [`Self::extract_if`], [`Self::drain_filter`], [`Self::drain`], [`Self::swap`],
+    /// [`Self::swap_indices`], any `sort*`) break that guarantee going forward; call
+    /// [`Self::compact`] afterwards to restore a deterministic (if not necessarily
+    /// original-insertion) order.
+    ///
+    /// The dense storage is contiguous, so the iterator is double-ended, reports an exact
+    /// `len()`, and is fused.
+    pub fn iter_in_insertion_order(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (SparseKey, &T)> + ExactSizeIterator + FusedIterator {
+        self.key_values()
+    }
 
-        // as long as we have available epochs, we can reuse the sparse entry
-        if key.epoch < MAX_EPOCH {
-            self.next_free_sparse_entry = key.sparse_index;
-        }
+    /// Consumes the set and returns an iterator over its values, in dense order.
+    pub fn into_values(self) -> impl Iterator<Item = T> {
+        self.into_iter()
     }
 
-    fn project_dense_key_to_sparse(&mut self, dense_index: usize) {
-        let key = self.storage.get_dense_keys()[dense_index];
-        self.storage.get_sparse_mut()[key.sparse_index] =
-            SparseEntry::new_alive(dense_index, key.epoch)
+    /// Consumes the set and returns an iterator over its keys, in dense order.
+    pub fn into_keys(self) -> impl Iterator<Item = SparseKey> {
+        self.storage.get_dense_keys().to_vec().into_iter()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns the sparse entry `key` points to, or `None` if its slot was already reclaimed by
+    /// a previous [`Self::shrink_to_fit`]/[`Self::shrink_to`] call, in which case `key` is
+    /// necessarily stale (reclaimed slots are always dead, and are never handed back out).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key.sparse_index()` is out of bounds even against the high-water mark; this
+    /// can happen only if the key is from another SparseSet.
+    fn resolve_sparse_entry(&self, key: SparseKey) -> Option<SparseEntry<I>> {
+        // a key from a generation before the last `clear_retaining_capacity` is invalid no matter
+        // what its sparse index points at now, since that slot may have already been handed out
+        // again under the current generation
+        if key.generation != self.generation {
+            return None;
+        }
 
-    // empty sparse set => created => no items
+        // this can happen only if the key is from another SparseSet
+        // in this case nothing is guaranteed anymore, we should panic
+        assert!(key.sparse_index() < self.high_water_mark.max(self.storage.get_sparse_len()));
+
+        if key.sparse_index() >= self.storage.get_sparse_len() {
+            return None;
+        }
+        Some(self.storage.get_sparse()[key.sparse_index()])
+    }
+
+    fn mark_as_free(&mut self, key: SparseKey) {
+        self.storage.get_sparse_mut()[key.sparse_index()].mark_free(self.next_free_sparse_entry);
+
+        // as long as we have available epochs, we can reuse the sparse entry; once the epoch
+        // counter is exhausted, retire the slot permanently instead of returning it to the free
+        // list, so a future key can never alias a stale one that held this slot before
+        if key.epoch < I::MAX_EPOCH {
+            self.next_free_sparse_entry = key.sparse_index();
+        } else {
+            self.retired_slot_count += 1;
+        }
+    }
+
+    fn project_dense_key_to_sparse(&mut self, dense_index: usize) {
+        let key = self.storage.get_dense_keys()[dense_index];
+        self.storage.get_sparse_mut()[key.sparse_index()] =
+            SparseEntry::new_alive(dense_index, key.epoch)
+    }
+}
+
+/// Iterator returned by [`SparseSet::drain`].
+struct Drain<'a, T, I: SparseIndex> {
+    set: &'a mut SparseSet<T, I>,
+}
+
+impl<T, I: SparseIndex> Iterator for Drain<'_, T, I> {
+    type Item = (SparseKey, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dense_len = self.set.storage.get_dense_len();
+        if dense_len == 0 {
+            return None;
+        }
+
+        let last_index = dense_len - 1;
+        let key = self.set.storage.get_dense_keys()[last_index];
+        let value = self.set.storage.swap_remove_dense(last_index);
+        self.set.mark_as_free(key);
+        Some((key, value))
+    }
+}
+
+impl<T, I: SparseIndex> Drop for Drain<'_, T, I> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
+
+/// Iterator returned by [`SparseSet::drain_filter`].
+struct DrainFilter<'a, T, I: SparseIndex, F> {
+    set: &'a mut SparseSet<T, I>,
+    index: usize,
+    predicate: F,
+}
+
+impl<T, I: SparseIndex, F> Iterator for DrainFilter<'_, T, I, F>
+where
+    F: FnMut(SparseKey, &mut T) -> bool,
+{
+    type Item = (SparseKey, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.set.storage.get_dense_len() {
+            let key = self.set.storage.get_dense_keys()[self.index];
+            let matches =
+                (self.predicate)(key, &mut self.set.storage.get_dense_values_mut()[self.index]);
+            if matches {
+                let value = self
+                    .set
+                    .swap_remove(key)
+                    .expect("key was just read from the dense array");
+                return Some((key, value));
+            }
+            self.index += 1;
+        }
+        None
+    }
+}
+
+impl<T, I: SparseIndex> Default for SparseSet<T, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SparseIndex> FromIterator<T> for SparseSet<T, I> {
+    fn from_iter<Iter: IntoIterator<Item = T>>(iter: Iter) -> Self {
+        let iter = iter.into_iter();
+        let mut set = Self::with_capacity(iter.size_hint().0);
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T, I: SparseIndex> Extend<T> for SparseSet<T, I> {
+    fn extend<Iter: IntoIterator<Item = T>>(&mut self, iter: Iter) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, I: SparseIndex> IntoIterator for SparseSet<T, I> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    /// Consumes the set and returns an iterator over its values, in dense order.
+    fn into_iter(self) -> Self::IntoIter {
+        self.storage.into_dense_values().into_iter()
+    }
+}
+
+impl<'a, T, I: SparseIndex> IntoIterator for &'a SparseSet<T, I> {
+    type Item = &'a T;
+    type IntoIter = Box<dyn DoubleEndedIterator<Item = &'a T> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.values())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // empty sparse set => created => no items
     #[test]
     fn empty_sparse_set_created_no_items() {
         let sparse_set: SparseSet<i32> = SparseSet::new();
@@ -771,6 +1612,65 @@ mod tests {
         assert_eq!(sparse_set.contains(key6), true);
     }
 
+    // sparse set with three items => clear retaining capacity => no items and capacity is unchanged
+    #[test]
+    fn sparse_set_with_three_items_clear_retaining_capacity_no_items_and_capacity_is_unchanged() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(42);
+        sparse_set.push(43);
+        sparse_set.push(44);
+        let capacity_before = sparse_set.capacity();
+
+        sparse_set.clear_retaining_capacity();
+
+        assert_eq!(sparse_set.len(), 0);
+        assert_eq!(sparse_set.capacity(), capacity_before);
+    }
+
+    // sparse set with three items => clear retaining capacity and add new items => old keys are invalid
+    #[test]
+    fn sparse_set_with_three_items_clear_retaining_capacity_and_add_new_items_old_keys_are_invalid()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+        let key3 = sparse_set.push(44);
+
+        sparse_set.clear_retaining_capacity();
+        let key4 = sparse_set.push(45);
+        let key5 = sparse_set.push(46);
+        let key6 = sparse_set.push(47);
+
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.contains(key1), false);
+        assert_eq!(sparse_set.contains(key2), false);
+        assert_eq!(sparse_set.contains(key3), false);
+        assert_eq!(sparse_set.contains(key4), true);
+        assert_eq!(sparse_set.contains(key5), true);
+        assert_eq!(sparse_set.contains(key6), true);
+    }
+
+    // sparse set with items cleared retaining capacity => push beyond old length => no reallocation happens
+    #[test]
+    fn sparse_set_with_items_cleared_retaining_capacity_push_beyond_old_length_no_reallocation_happens(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+        let capacity_before = sparse_set.capacity();
+
+        sparse_set.clear_retaining_capacity();
+        for value in 0..capacity_before {
+            sparse_set.push(value as i32);
+        }
+        // pushing one more element past the retained capacity should be the first one to grow it
+        sparse_set.push(-1);
+
+        assert_eq!(sparse_set.len(), capacity_before + 1);
+        assert!(sparse_set.capacity() > capacity_before);
+    }
+
     // sparse set with three items => get index => the expected index is returned
     #[test]
     fn sparse_set_with_three_items_get_index_the_expected_index_is_returned() {
@@ -784,6 +1684,17 @@ mod tests {
         assert_eq!(sparse_set.index(key3), Some(2));
     }
 
+    // sparse set with three items => index_of => agrees with index
+    #[test]
+    fn sparse_set_with_three_items_index_of_agrees_with_index() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+
+        assert_eq!(sparse_set.index_of(key1), Some(0));
+        assert_eq!(sparse_set.index_of(key2), Some(1));
+    }
+
     // sparse set with two items => remove item and get its index => returns None
     #[test]
     fn sparse_set_with_two_items_remove_item_and_get_its_index_returns_none() {
@@ -1587,6 +2498,29 @@ mod tests {
         assert_eq!(sparse_set.contains(key6), true);
     }
 
+    // sparse set of strings with three items => clear retaining capacity and add new items => old keys are invalid
+    #[test]
+    fn sparse_set_of_strings_with_three_items_clear_retaining_capacity_and_add_new_items_old_keys_are_invalid(
+    ) {
+        let mut sparse_set: SparseSet<String> = SparseSet::new();
+        let key1 = sparse_set.push("42".to_string());
+        let key2 = sparse_set.push("43".to_string());
+        let key3 = sparse_set.push("44".to_string());
+
+        sparse_set.clear_retaining_capacity();
+        let key4 = sparse_set.push("45".to_string());
+        let key5 = sparse_set.push("46".to_string());
+        let key6 = sparse_set.push("47".to_string());
+
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.contains(key1), false);
+        assert_eq!(sparse_set.contains(key2), false);
+        assert_eq!(sparse_set.contains(key3), false);
+        assert_eq!(sparse_set.contains(key4), true);
+        assert_eq!(sparse_set.contains(key5), true);
+        assert_eq!(sparse_set.contains(key6), true);
+    }
+
     // sparse set of strings with three items => iterate over values => the values are iterated in order
     #[test]
     fn sparse_set_of_strings_with_three_items_iterate_over_values_the_values_are_iterated_in_order()
@@ -2074,4 +3008,1129 @@ mod tests {
         fn is_sync<T: Sync>() {}
         is_sync::<SparseSet<i32>>();
     }
+
+    // empty sparse set => push front item => has one item
+    #[test]
+    fn empty_sparse_set_push_front_item_has_one_item() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        let key = sparse_set.push_front(42);
+
+        assert_eq!(sparse_set.len(), 1);
+        assert_eq!(sparse_set.get_key(0), Some(key));
+        assert_eq!(sparse_set.get(key), Some(&42));
+    }
+
+    // sparse set with one item => push front second item => second item is first in order
+    #[test]
+    fn sparse_set_with_one_item_push_front_second_item_second_item_is_first_in_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+
+        let key2 = sparse_set.push_front(43);
+
+        assert_eq!(sparse_set.len(), 2);
+        assert_eq!(sparse_set.get_key(0), Some(key2));
+        assert_eq!(sparse_set.get_key(1), Some(key1));
+        assert_eq!(sparse_set.get(key1), Some(&42));
+        assert_eq!(sparse_set.get(key2), Some(&43));
+    }
+
+    // sparse set with three items => pop front => first item removed, order preserved
+    #[test]
+    fn sparse_set_with_three_items_pop_front_first_item_removed_order_preserved() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+        let key3 = sparse_set.push(44);
+
+        assert_eq!(sparse_set.pop_front(), Some(42));
+
+        assert_eq!(sparse_set.len(), 2);
+        assert_eq!(sparse_set.get_key(0), Some(key2));
+        assert_eq!(sparse_set.get_key(1), Some(key3));
+        assert_eq!(sparse_set.get(key1), None);
+        assert_eq!(sparse_set.get(key2), Some(&43));
+        assert_eq!(sparse_set.get(key3), Some(&44));
+    }
+
+    // empty sparse set => pop front => returns none
+    #[test]
+    fn empty_sparse_set_pop_front_returns_none() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        assert_eq!(sparse_set.pop_front(), None);
+    }
+
+    // sparse set with capacity => shrink to fit after removals => surviving keys still resolve
+    #[test]
+    fn sparse_set_with_capacity_shrink_to_fit_after_removals_surviving_keys_still_resolve() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::with_capacity(16);
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+        sparse_set.push(44);
+
+        sparse_set.remove(key2);
+        sparse_set.shrink_to_fit();
+
+        assert_eq!(sparse_set.len(), 2);
+        assert_eq!(sparse_set.get(key1), Some(&42));
+        assert_eq!(sparse_set.get(key2), None);
+    }
+
+    // empty sparse set => shrink to fit => does not panic
+    #[test]
+    fn empty_sparse_set_shrink_to_fit_does_not_panic() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        sparse_set.shrink_to_fit();
+
+        assert_eq!(sparse_set.len(), 0);
+    }
+
+    // empty sparse set => created => no retired slots
+    #[test]
+    fn empty_sparse_set_created_no_retired_slots() {
+        let sparse_set: SparseSet<i32> = SparseSet::new();
+
+        assert_eq!(sparse_set.retired_slot_count(), 0);
+    }
+
+    // sparse set with one item => remove and reinsert a few times => no retired slots
+    #[test]
+    fn sparse_set_with_one_item_remove_and_reinsert_a_few_times_no_retired_slots() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        for _ in 0..8 {
+            let key = sparse_set.push(42);
+            sparse_set.remove(key);
+        }
+
+        assert_eq!(sparse_set.retired_slot_count(), 0);
+    }
+
+    // empty sparse set with no retired slots => reclaim retired => does not panic and stays empty
+    #[test]
+    fn empty_sparse_set_with_no_retired_slots_reclaim_retired_does_not_panic_and_stays_empty() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        sparse_set.reclaim_retired();
+
+        assert_eq!(sparse_set.len(), 0);
+        assert_eq!(sparse_set.retired_slot_count(), 0);
+    }
+
+    // sparse set with one item => reclaim retired => panics
+    #[test]
+    #[should_panic]
+    fn sparse_set_with_one_item_reclaim_retired_panics() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(42);
+
+        sparse_set.reclaim_retired();
+    }
+
+    // sparse set => from iterator => collects values in push order
+    #[test]
+    fn sparse_set_from_iterator_collects_values_in_push_order() {
+        let sparse_set: SparseSet<i32> = (0..5).collect();
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+    }
+
+    // sparse set with one item => extend with values => values appended in order
+    #[test]
+    fn sparse_set_with_one_item_extend_with_values_values_appended_in_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+
+        sparse_set.extend([2, 3, 4]);
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    // sparse set with three items => into iter => yields values in dense order
+    #[test]
+    fn sparse_set_with_three_items_into_iter_yields_values_in_dense_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        let values: Vec<i32> = sparse_set.into_iter().collect();
+
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    // sparse set with three items => into values => yields values in dense order
+    #[test]
+    fn sparse_set_with_three_items_into_values_yields_values_in_dense_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        let values: Vec<i32> = sparse_set.into_values().collect();
+
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    // sparse set with three items => into keys => yields keys in dense order
+    #[test]
+    fn sparse_set_with_three_items_into_keys_yields_keys_in_dense_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        let keys: Vec<SparseKey> = sparse_set.into_keys().collect();
+
+        assert_eq!(keys, vec![key1, key2, key3]);
+    }
+
+    // sparse set with three items => into iter by reference => yields references in dense order
+    #[test]
+    fn sparse_set_with_three_items_into_iter_by_reference_yields_references_in_dense_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        let values: Vec<i32> = (&sparse_set).into_iter().copied().collect();
+
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    // sparse set with unsorted items => sort => values are in ascending order and keys still resolve
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_values_are_in_ascending_order_and_keys_still_resolve() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key3 = sparse_set.push(3);
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+
+        sparse_set.sort();
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set with unsorted items => sort_by ascending => values are in ascending order
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_by_ascending_values_are_in_ascending_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(3);
+        sparse_set.push(1);
+        sparse_set.push(2);
+
+        sparse_set.sort_by(|a, b| a.cmp(b));
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    // sparse set with unsorted items => sort_by ascending => existing keys still resolve to their element
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_by_ascending_existing_keys_still_resolve_to_their_element(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key3 = sparse_set.push(3);
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+
+        sparse_set.sort_by(|a, b| a.cmp(b));
+
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set with unsorted items => sort_unstable_by ascending => values are in ascending order
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_unstable_by_ascending_values_are_in_ascending_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(3);
+        sparse_set.push(1);
+        sparse_set.push(2);
+
+        sparse_set.sort_unstable_by(|a, b| a.cmp(b));
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    // sparse set with unsorted items => sort_unstable_by ascending => existing keys still resolve to their element
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_unstable_by_ascending_existing_keys_still_resolve_to_their_element(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key3 = sparse_set.push(3);
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+
+        sparse_set.sort_unstable_by(|a, b| a.cmp(b));
+
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set with three items => get_index => returns the value at the dense position
+    #[test]
+    fn sparse_set_with_three_items_get_index_returns_the_value_at_the_dense_position() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        assert_eq!(sparse_set.get_index(1), Some(&2));
+        assert_eq!(sparse_set.get_index(3), None);
+    }
+
+    // sparse set with three items => get_index_mut => allows mutating the value in place
+    #[test]
+    fn sparse_set_with_three_items_get_index_mut_allows_mutating_the_value_in_place() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        *sparse_set.get_index_mut(1).unwrap() = 42;
+
+        assert_eq!(sparse_set.get_index(1), Some(&42));
+    }
+
+    // sparse set with three items => swap_indices => values are swapped and keys still resolve
+    #[test]
+    fn sparse_set_with_three_items_swap_indices_values_are_swapped_and_keys_still_resolve() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        sparse_set.push(3);
+
+        sparse_set.swap_indices(0, 1);
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![2, 1, 3]);
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+    }
+
+    // sparse set with several items => retain even values => only even values remain in order
+    #[test]
+    fn sparse_set_with_several_items_retain_even_values_only_even_values_remain_in_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        for i in 0..6 {
+            sparse_set.push(i);
+        }
+
+        sparse_set.retain(|_key, value| *value % 2 == 0);
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![0, 2, 4]);
+    }
+
+    // sparse set with several items => retain first and last => relative order of survivors is preserved
+    #[test]
+    fn sparse_set_with_several_items_retain_first_and_last_relative_order_of_survivors_is_preserved(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        for i in 0..5 {
+            sparse_set.push(i);
+        }
+
+        sparse_set.retain(|_key, value| *value == 0 || *value == 1 || *value == 4);
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![0, 1, 4]);
+    }
+
+    // sparse set with several items => retain even values => removed keys no longer resolve
+    #[test]
+    fn sparse_set_with_several_items_retain_even_values_removed_keys_no_longer_resolve() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let keys: Vec<SparseKey> = (0..6).map(|i| sparse_set.push(i)).collect();
+
+        sparse_set.retain(|_key, value| *value % 2 == 0);
+
+        for (value, key) in keys.into_iter().enumerate() {
+            if value % 2 == 0 {
+                assert_eq!(sparse_set.get(key), Some(&(value as i32)));
+            } else {
+                assert_eq!(sparse_set.get(key), None);
+            }
+        }
+    }
+
+    // sparse set with several items => extract_if odd values => returns the removed pairs
+    #[test]
+    fn sparse_set_with_several_items_extract_if_odd_values_returns_the_removed_pairs() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        for i in 0..6 {
+            sparse_set.push(i);
+        }
+
+        let mut extracted = sparse_set.extract_if(|_key, value| *value % 2 != 0);
+        let mut extracted_values: Vec<i32> = extracted.drain(..).map(|(_, value)| value).collect();
+        extracted_values.sort_unstable();
+
+        assert_eq!(extracted_values, vec![1, 3, 5]);
+        let mut remaining_values = sparse_set.values().copied().collect::<Vec<_>>();
+        remaining_values.sort_unstable();
+        assert_eq!(remaining_values, vec![0, 2, 4]);
+    }
+
+    // sparse set with several items => drain_filter odd values => behaves like extract_if
+    #[test]
+    fn sparse_set_with_several_items_drain_filter_odd_values_behaves_like_extract_if() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        for i in 0..6 {
+            sparse_set.push(i);
+        }
+
+        let mut extracted_values: Vec<i32> = sparse_set
+            .drain_filter(|_key, value| *value % 2 != 0)
+            .map(|(_, value)| value)
+            .collect();
+        extracted_values.sort_unstable();
+
+        assert_eq!(extracted_values, vec![1, 3, 5]);
+        let mut remaining_values = sparse_set.values().copied().collect::<Vec<_>>();
+        remaining_values.sort_unstable();
+        assert_eq!(remaining_values, vec![0, 2, 4]);
+    }
+
+    // sparse set with several items => drain_filter dropped after one step => untouched elements keep their keys
+    #[test]
+    fn sparse_set_with_several_items_drain_filter_dropped_after_one_step_untouched_elements_keep_their_keys(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let keys: Vec<SparseKey> = (0..6).map(|i| sparse_set.push(i)).collect();
+
+        {
+            let mut iter = sparse_set.drain_filter(|_key, _value| true);
+            assert!(iter.next().is_some());
+        }
+
+        // only the single element the predicate was actually asked about was removed; the rest
+        // of the set, never visited, is untouched and every remaining key still resolves
+        assert_eq!(sparse_set.len(), 5);
+        let alive_count = keys.iter().filter(|key| sparse_set.get(**key).is_some()).count();
+        assert_eq!(alive_count, 5);
+    }
+
+    // empty sparse set => created with capacity => capacity reflects the requested amount
+    #[test]
+    fn empty_sparse_set_created_with_capacity_capacity_reflects_the_requested_amount() {
+        let sparse_set: SparseSet<i32> = SparseSet::with_capacity(10);
+
+        assert!(sparse_set.capacity() >= 10);
+    }
+
+    // empty sparse set => reserve additional capacity => capacity grows to cover it
+    #[test]
+    fn empty_sparse_set_reserve_additional_capacity_capacity_grows_to_cover_it() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        sparse_set.reserve(10);
+
+        assert!(sparse_set.capacity() >= 10);
+
+        sparse_set.reserve(0);
+
+        assert!(sparse_set.capacity() >= 10);
+    }
+
+    // sparse set with capacity and items removed => shrink_to_fit => capacity collapses to the live elements
+    #[test]
+    fn sparse_set_with_capacity_and_items_removed_shrink_to_fit_capacity_collapses_to_the_live_elements(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::with_capacity(10);
+        let key1 = sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.remove(key1);
+
+        sparse_set.shrink_to_fit();
+
+        // one sparse slot is still allocated for the removed key, even though only one element
+        // is alive, since reclaiming it would renumber the slot after it and invalidate its key
+        assert_eq!(sparse_set.capacity(), 2);
+    }
+
+    // sparse set with capacity and the most recently pushed item removed => shrink_to_fit => capacity collapses past the trailing dead slot
+    #[test]
+    fn sparse_set_with_trailing_removed_slot_shrink_to_fit_capacity_collapses_past_it() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::with_capacity(3);
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        // key3 occupies the last sparse slot, so removing it leaves a trailing dead slot with
+        // nothing alive after it, which shrink_to_fit can reclaim without renumbering anything
+        sparse_set.remove(key3);
+        sparse_set.shrink_to_fit();
+
+        assert_eq!(sparse_set.capacity(), 2);
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), None);
+
+        // the reclaimed slot can be handed back out to a new push without trouble
+        let key4 = sparse_set.push(4);
+        assert_eq!(sparse_set.get(key4), Some(&4));
+    }
+
+    // sparse set with many items pushed and most removed => shrink_to_fit => surviving keys resolve and capacity drops
+    #[test]
+    fn sparse_set_with_many_items_removed_shrink_to_fit_surviving_keys_resolve_and_capacity_drops() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let keys: Vec<SparseKey> = (0..100).map(|i| sparse_set.push(i)).collect();
+
+        let capacity_before = sparse_set.capacity();
+        for &key in &keys[1..] {
+            sparse_set.remove(key);
+        }
+        sparse_set.shrink_to_fit();
+
+        assert!(sparse_set.capacity() < capacity_before);
+        assert_eq!(sparse_set.get(keys[0]), Some(&0));
+        for &key in &keys[1..] {
+            assert_eq!(sparse_set.get(key), None);
+        }
+    }
+
+    // empty sparse set => reserve_exact additional capacity => capacity grows to cover it
+    #[test]
+    fn empty_sparse_set_reserve_exact_additional_capacity_capacity_grows_to_cover_it() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        sparse_set.reserve_exact(10);
+
+        assert!(sparse_set.capacity() >= 10);
+    }
+
+    // empty sparse set => push_full => returns the dense index alongside the key
+    #[test]
+    fn empty_sparse_set_push_full_returns_the_dense_index_alongside_the_key() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+
+        let (index1, key1) = sparse_set.push_full(42);
+        let (index2, key2) = sparse_set.push_full(43);
+
+        assert_eq!((index1, key1), (0, sparse_set.get_key(0).unwrap()));
+        assert_eq!((index2, key2), (1, sparse_set.get_key(1).unwrap()));
+    }
+
+    // sparse set with three items => get_full => returns the dense index and the value together
+    #[test]
+    fn sparse_set_with_three_items_get_full_returns_the_dense_index_and_the_value_together() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+
+        assert_eq!(sparse_set.get_full(key1), Some((0, &42)));
+        assert_eq!(sparse_set.get_full(key2), Some((1, &43)));
+    }
+
+    // sparse set with one item => remove item then get_full => returns None
+    #[test]
+    fn sparse_set_with_one_item_remove_item_then_get_full_returns_none() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key = sparse_set.push(42);
+
+        sparse_set.remove(key);
+
+        assert_eq!(sparse_set.get_full(key), None);
+    }
+
+    // sparse set with one item => get_full_mut => allows mutating the value in place
+    #[test]
+    fn sparse_set_with_one_item_get_full_mut_allows_mutating_the_value_in_place() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key = sparse_set.push(42);
+
+        let (index, value) = sparse_set.get_full_mut(key).unwrap();
+        *value = 43;
+
+        assert_eq!(index, 0);
+        assert_eq!(sparse_set.get(key), Some(&43));
+    }
+
+    // sparse set with interleaved removals and reused slots => serde round trip => keys still resolve
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sparse_set_with_interleaved_removals_and_reused_slots_serde_round_trip_keys_still_resolve()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        sparse_set.remove(key1);
+        let key3 = sparse_set.push(3);
+        let key4 = sparse_set.push(4);
+        sparse_set.remove(key2);
+
+        let serialized = serde_json::to_string(&sparse_set).unwrap();
+        let deserialized: SparseSet<i32> = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized.get(key1), None);
+        assert_eq!(deserialized.get(key2), None);
+        assert_eq!(deserialized.get(key3), Some(&3));
+        assert_eq!(deserialized.get(key4), Some(&4));
+        assert_eq!(deserialized.len(), sparse_set.len());
+    }
+
+    // sparse set with interleaved removals and reused slots => serde round trip => future pushes reuse the same slots
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sparse_set_with_interleaved_removals_and_reused_slots_serde_round_trip_future_pushes_reuse_the_same_slots(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        sparse_set.remove(key1);
+
+        let serialized = serde_json::to_string(&sparse_set).unwrap();
+        let mut deserialized: SparseSet<i32> = serde_json::from_str(&serialized).unwrap();
+
+        let key1_after_round_trip = sparse_set.push(5);
+        let key1_after_deserialize = deserialized.push(5);
+
+        assert_eq!(key1_after_round_trip, key1_after_deserialize);
+    }
+
+    // serialized sparse set with a corrupted free list => deserialize => returns an error
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_sparse_set_with_a_corrupted_free_list_deserialize_returns_an_error() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.remove(key1);
+
+        let mut value: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&sparse_set).unwrap()).unwrap();
+        // point the free-list head past the end of the sparse table instead of at the one free slot
+        value["next_free_sparse_entry"] = serde_json::json!(999);
+
+        let result: Result<SparseSet<i32>, _> = serde_json::from_value(value);
+
+        assert!(result.is_err());
+    }
+
+    // serialized sparse set with a duplicated dense key => deserialize => returns an error
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_sparse_set_with_a_duplicated_dense_key_deserialize_returns_an_error() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+
+        let mut value: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&sparse_set).unwrap()).unwrap();
+        // make the second dense key a copy of the first, so its sparse slot is claimed twice
+        // while the slot the second key used to claim is left orphaned
+        value["dense_keys"][1] = value["dense_keys"][0].clone();
+
+        let result: Result<SparseSet<i32>, _> = serde_json::from_value(value);
+
+        assert!(result.is_err());
+    }
+
+    // sparse set with unsorted items => sort_by_key descending => values are in descending order
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_by_key_descending_values_are_in_descending_order() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(3);
+        sparse_set.push(2);
+
+        sparse_set.sort_by_key(|value| std::cmp::Reverse(*value));
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+
+    // sparse set with unsorted items => sort_by_key descending => existing keys still resolve to their element
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_by_key_descending_existing_keys_still_resolve_to_their_element(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key3 = sparse_set.push(3);
+        let key2 = sparse_set.push(2);
+
+        sparse_set.sort_by_key(|value| std::cmp::Reverse(*value));
+
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set with unsorted items => sort_unstable_by_key descending => values are in descending order and keys still resolve
+    #[test]
+    fn sparse_set_with_unsorted_items_sort_unstable_by_key_descending_values_are_in_descending_order_and_keys_still_resolve(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key3 = sparse_set.push(3);
+        let key2 = sparse_set.push(2);
+
+        sparse_set.sort_unstable_by_key(|value| std::cmp::Reverse(*value));
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set reordered by value => sort_keys => dense order matches key order and keys still resolve
+    #[test]
+    fn sparse_set_reordered_by_value_sort_keys_dense_order_matches_key_order_and_keys_still_resolve() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        sparse_set.sort_by(|a, b| b.cmp(a));
+        sparse_set.sort_keys();
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(sparse_set.get(key1), Some(&1));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+    }
+
+    // sparse set reordered by swap_remove => compact => dense order matches sparse-slot order, keys still resolve, remap matches the new positions
+    #[test]
+    fn sparse_set_reordered_by_swap_remove_compact_dense_order_matches_insertion_order_keys_still_resolve_remap_matches_the_new_positions()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        // swap_remove(key1) moves key3's value into position 0, leaving dense order [3, 2]
+        sparse_set.swap_remove(key1);
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![3, 2]);
+
+        let remap = sparse_set.compact();
+
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+        // key3's value was at dense index 0 before compacting, and key2's at index 1
+        assert_eq!(remap[0], 1);
+        assert_eq!(remap[1], 0);
+    }
+
+    // sparse set with a reused sparse slot => compact => dense order follows slot identity, not true creation order
+    #[test]
+    fn sparse_set_with_a_reused_sparse_slot_compact_dense_order_follows_slot_identity_not_creation_order()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        // order-preserving remove of key1 defragments the dense array in place, so dense order
+        // is still true creation order: [2, 3]
+        sparse_set.remove(key1);
+        // push(4) reuses key1's freed, low sparse_index, so dense order remains true creation
+        // order, [2, 3, 4], right up until compact() is called
+        let key4 = sparse_set.push(4);
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        sparse_set.compact();
+
+        // key4 reused the lowest sparse_index, so it sorts first even though it was created
+        // last: compact() orders by slot identity, not by when each element was pushed
+        assert_eq!(sparse_set.values().copied().collect::<Vec<_>>(), vec![4, 2, 3]);
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&3));
+        assert_eq!(sparse_set.get(key4), Some(&4));
+    }
+
+    // sparse set with three items => iterate in insertion order => the key-values are iterated in insertion order
+    #[test]
+    fn sparse_set_with_three_items_iterate_in_insertion_order_the_key_values_are_iterated_in_insertion_order()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(42);
+        let key2 = sparse_set.push(43);
+        let key3 = sparse_set.push(44);
+
+        let collected: Vec<_> = sparse_set.iter_in_insertion_order().collect();
+
+        assert_eq!(collected, vec![(key1, &42), (key2, &43), (key3, &44)]);
+    }
+
+    // sparse set with three items => get_disjoint_mut distinct keys => allows mutating all at once
+    #[test]
+    fn sparse_set_with_three_items_get_disjoint_mut_distinct_keys_allows_mutating_all_at_once() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        let [value1, value3] = sparse_set.get_disjoint_mut([key1, key3]).unwrap();
+        *value1 += 10;
+        *value3 += 10;
+
+        assert_eq!(sparse_set.get(key1), Some(&11));
+        assert_eq!(sparse_set.get(key2), Some(&2));
+        assert_eq!(sparse_set.get(key3), Some(&13));
+    }
+
+    // sparse set with one item => get_disjoint_mut with the same key twice => returns none
+    #[test]
+    fn sparse_set_with_one_item_get_disjoint_mut_with_the_same_key_twice_returns_none() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key = sparse_set.push(1);
+
+        assert!(sparse_set.get_disjoint_mut([key, key]).is_none());
+    }
+
+    // sparse set with one item => get_disjoint_mut after removal => returns none
+    #[test]
+    fn sparse_set_with_one_item_get_disjoint_mut_after_removal_returns_none() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        sparse_set.remove(key1);
+
+        assert!(sparse_set.get_disjoint_mut([key1, key2]).is_none());
+    }
+
+    // sparse set with three items => drain fully consumed => yields every key and value and leaves the set empty
+    #[test]
+    fn sparse_set_with_three_items_drain_fully_consumed_yields_every_key_and_value_and_leaves_the_set_empty(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        let mut drained: Vec<(SparseKey, i32)> = sparse_set.drain().collect();
+        drained.sort_by_key(|(_, value)| *value);
+
+        assert_eq!(drained, vec![(key1, 1), (key2, 2), (key3, 3)]);
+        assert!(sparse_set.is_empty());
+    }
+
+    // sparse set with three items => drain dropped after one item => remaining elements are still removed
+    #[test]
+    fn sparse_set_with_three_items_drain_dropped_after_one_item_remaining_elements_are_still_removed(
+    ) {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(1);
+        sparse_set.push(2);
+        sparse_set.push(3);
+
+        {
+            let mut drain = sparse_set.drain();
+            drain.next();
+        }
+
+        assert!(sparse_set.is_empty());
+    }
+
+    // sparse set with one item => drain then push => reused slot has the next epoch
+    #[test]
+    fn sparse_set_with_one_item_drain_then_push_reused_slot_has_the_next_epoch() {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key = sparse_set.push(1);
+        sparse_set.drain().for_each(drop);
+
+        let new_key = sparse_set.push(2);
+
+        assert_eq!(sparse_set.get(key), None);
+        assert_eq!(sparse_set.get(new_key), Some(&2));
+    }
+
+    // sparse key => check niche optimization => option does not grow the size
+    #[test]
+    fn sparse_key_check_niche_optimization_option_does_not_grow_the_size() {
+        assert_eq!(size_of::<Option<SparseKey>>(), size_of::<SparseKey>());
+    }
+
+    // sparse key => construct at the largest sparse index the default index type allows => does not panic and round trips
+    #[test]
+    fn sparse_key_construct_at_the_largest_legal_sparse_index_does_not_panic_and_round_trips() {
+        let largest_index = u32::MAX_SPARSE_INDEX;
+        let key = SparseKey::new(largest_index, 0, 0);
+
+        assert_eq!(key.sparse_index(), largest_index);
+        assert_eq!(Some(key).map(|key| key.sparse_index()), Some(largest_index));
+    }
+
+    // two overlapping sparse sets => union => contains every distinct value once
+    #[test]
+    fn two_overlapping_sparse_sets_union_contains_every_distinct_value_once() {
+        let a: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3, 4].into_iter().collect();
+
+        let mut result: Vec<i32> = a.union(&b).values().copied().collect();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![1, 2, 3, 4]);
+    }
+
+    // two overlapping sparse sets => intersection => contains only the shared values
+    #[test]
+    fn two_overlapping_sparse_sets_intersection_contains_only_the_shared_values() {
+        let a: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3, 4].into_iter().collect();
+
+        let mut result: Vec<i32> = a.intersection(&b).values().copied().collect();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![2, 3]);
+    }
+
+    // two overlapping sparse sets => difference => contains only the values unique to self
+    #[test]
+    fn two_overlapping_sparse_sets_difference_contains_only_the_values_unique_to_self() {
+        let a: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3, 4].into_iter().collect();
+
+        let result: Vec<i32> = a.difference(&b).values().copied().collect();
+
+        assert_eq!(result, vec![1]);
+    }
+
+    // two overlapping sparse sets => symmetric_difference => contains values unique to either side
+    #[test]
+    fn two_overlapping_sparse_sets_symmetric_difference_contains_values_unique_to_either_side() {
+        let a: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3, 4].into_iter().collect();
+
+        let mut result: Vec<i32> = a.symmetric_difference(&b).values().copied().collect();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![1, 4]);
+    }
+
+    // two disjoint sparse sets => is_disjoint => returns true
+    #[test]
+    fn two_disjoint_sparse_sets_is_disjoint_returns_true() {
+        let a: SparseSet<i32> = [1, 2].into_iter().collect();
+        let b: SparseSet<i32> = [3, 4].into_iter().collect();
+
+        assert!(a.is_disjoint(&b));
+    }
+
+    // two overlapping sparse sets => is_disjoint => returns false
+    #[test]
+    fn two_overlapping_sparse_sets_is_disjoint_returns_false() {
+        let a: SparseSet<i32> = [1, 2].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3].into_iter().collect();
+
+        assert!(!a.is_disjoint(&b));
+    }
+
+    // sparse set with a subset of another's values => is_subset => returns true
+    #[test]
+    fn sparse_set_with_a_subset_of_another_values_is_subset_returns_true() {
+        let a: SparseSet<i32> = [1, 2].into_iter().collect();
+        let b: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+
+        assert!(a.is_subset(&b));
+        assert!(b.is_superset(&a));
+    }
+
+    // sparse set with a value missing from another => is_subset => returns false
+    #[test]
+    fn sparse_set_with_a_value_missing_from_another_is_subset_returns_false() {
+        let a: SparseSet<i32> = [1, 2, 5].into_iter().collect();
+        let b: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+
+        assert!(!a.is_subset(&b));
+        assert!(!b.is_superset(&a));
+    }
+
+    // two overlapping sparse sets => union_hashed/intersection_hashed/difference_hashed => agree with the O(n*m) versions
+    #[test]
+    fn two_overlapping_sparse_sets_hashed_set_ops_agree_with_the_o_n_m_versions() {
+        let a: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+        let b: SparseSet<i32> = [2, 3, 4].into_iter().collect();
+
+        let mut union = a.union_hashed(&b).values().copied().collect::<Vec<_>>();
+        union.sort_unstable();
+        assert_eq!(union, vec![1, 2, 3, 4]);
+
+        let mut intersection = a.intersection_hashed(&b).values().copied().collect::<Vec<_>>();
+        intersection.sort_unstable();
+        assert_eq!(intersection, vec![2, 3]);
+
+        assert_eq!(a.difference_hashed(&b).values().copied().collect::<Vec<_>>(), vec![1]);
+
+        let mut symmetric_difference =
+            a.symmetric_difference_hashed(&b).values().copied().collect::<Vec<_>>();
+        symmetric_difference.sort_unstable();
+        assert_eq!(symmetric_difference, vec![1, 4]);
+    }
+
+    // two disjoint sparse sets => is_disjoint_hashed => returns true; overlapping => returns false
+    #[test]
+    fn two_sparse_sets_is_disjoint_hashed_agrees_with_the_o_n_m_version() {
+        let a: SparseSet<i32> = [1, 2].into_iter().collect();
+        let b: SparseSet<i32> = [3, 4].into_iter().collect();
+        let c: SparseSet<i32> = [2, 3].into_iter().collect();
+
+        assert!(a.is_disjoint_hashed(&b));
+        assert!(!a.is_disjoint_hashed(&c));
+    }
+
+    // sparse set with a subset of another's values => is_subset_hashed/is_superset_hashed => agree with the O(n*m) versions
+    #[test]
+    fn sparse_set_is_subset_hashed_and_is_superset_hashed_agree_with_the_o_n_m_versions() {
+        let a: SparseSet<i32> = [1, 2].into_iter().collect();
+        let b: SparseSet<i32> = [1, 2, 3].into_iter().collect();
+
+        assert!(a.is_subset_hashed(&b));
+        assert!(b.is_superset_hashed(&a));
+        assert!(!b.is_subset_hashed(&a));
+        assert!(!a.is_superset_hashed(&b));
+    }
+
+    // two sparse sets of tuples sharing some ids => union_by_key/intersection_by_key/difference_by_key/symmetric_difference_by_key => match by the projected id, not the whole value
+    #[test]
+    fn two_sparse_sets_of_tuples_by_key_set_ops_match_by_the_projected_id() {
+        let a: SparseSet<(i32, &str)> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+        let b: SparseSet<(i32, &str)> = [(2, "z"), (3, "z"), (4, "z")].into_iter().collect();
+        let key_of = |value: &(i32, &str)| value.0;
+
+        let mut union: Vec<i32> = a.union_by_key(&b, key_of).values().map(|v| v.0).collect();
+        union.sort_unstable();
+        assert_eq!(union, vec![1, 2, 3, 4]);
+
+        let mut intersection: Vec<(i32, &str)> =
+            a.intersection_by_key(&b, key_of).values().copied().collect();
+        intersection.sort_unstable();
+        assert_eq!(intersection, vec![(2, "b"), (3, "c")]);
+
+        assert_eq!(
+            a.difference_by_key(&b, key_of).values().copied().collect::<Vec<_>>(),
+            vec![(1, "a")]
+        );
+
+        let mut symmetric_difference: Vec<i32> = a
+            .symmetric_difference_by_key(&b, key_of)
+            .values()
+            .map(|v| v.0)
+            .collect();
+        symmetric_difference.sort_unstable();
+        assert_eq!(symmetric_difference, vec![1, 4]);
+    }
+
+    // sparse set with three items => reverse values/keys/key_values iterators => yield elements back to front
+    #[test]
+    fn sparse_set_with_three_items_reverse_values_keys_key_values_iterators_yield_elements_back_to_front()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        let key1 = sparse_set.push(1);
+        let key2 = sparse_set.push(2);
+        let key3 = sparse_set.push(3);
+
+        assert_eq!(sparse_set.values().len(), 3);
+        assert_eq!(sparse_set.values().rev().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(sparse_set.keys().len(), 3);
+        assert_eq!(sparse_set.keys().rev().collect::<Vec<_>>(), vec![key3, key2, key1]);
+        assert_eq!(sparse_set.key_values().len(), 3);
+        assert_eq!(
+            sparse_set.key_values().rev().collect::<Vec<_>>(),
+            vec![(key3, &3), (key2, &2), (key1, &1)]
+        );
+        assert_eq!(sparse_set.values_mut().len(), 3);
+        assert_eq!(sparse_set.values_mut().rev().map(|v| *v).collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+
+    // sparse set with one item => exhaust values/keys/key_values iterators => next keeps returning None
+    #[test]
+    fn sparse_set_with_one_item_exhaust_values_keys_key_values_iterators_next_keeps_returning_none()
+    {
+        let mut sparse_set: SparseSet<i32> = SparseSet::new();
+        sparse_set.push(42);
+
+        {
+            let mut values = sparse_set.values();
+            assert!(values.next().is_some());
+            assert_eq!(values.next(), None);
+            assert_eq!(values.next(), None);
+        }
+
+        {
+            let mut keys = sparse_set.keys();
+            assert!(keys.next().is_some());
+            assert_eq!(keys.next(), None);
+            assert_eq!(keys.next(), None);
+        }
+
+        {
+            let mut key_values = sparse_set.key_values();
+            assert!(key_values.next().is_some());
+            assert_eq!(key_values.next(), None);
+            assert_eq!(key_values.next(), None);
+        }
+
+        {
+            let mut values_mut = sparse_set.values_mut();
+            assert!(values_mut.next().is_some());
+            assert_eq!(values_mut.next(), None);
+            assert_eq!(values_mut.next(), None);
+        }
+    }
+
+    // sparse set pair with an item in current => carry_over its key => reachable in next, gone from current, key still invalid in current
+    #[test]
+    fn sparse_set_pair_with_an_item_in_current_carry_over_its_key_reachable_in_next_gone_from_current()
+    {
+        let mut pair: SparseSetPair<i32> = SparseSetPair::new();
+        let key = pair.current_mut().push(42);
+
+        let carried_key = pair.carry_over(key).unwrap();
+
+        assert_eq!(pair.current().get(key), None);
+        assert_eq!(pair.next().get(carried_key), Some(&42));
+    }
+
+    // sparse set pair with a carried over item => swap => the carried key resolves against the new current
+    #[test]
+    fn sparse_set_pair_with_a_carried_over_item_swap_the_carried_key_resolves_against_the_new_current()
+    {
+        let mut pair: SparseSetPair<i32> = SparseSetPair::new();
+        let key = pair.current_mut().push(42);
+        let carried_key = pair.carry_over(key).unwrap();
+
+        pair.swap();
+
+        assert_eq!(pair.current().get(carried_key), Some(&42));
+        assert_eq!(pair.next().len(), 0);
+    }
+
+    // sparse set pair with an empty current => carry_over a non-existent key => returns None and leaves both sets untouched
+    #[test]
+    fn sparse_set_pair_with_an_empty_current_carry_over_a_non_existent_key_returns_none() {
+        let mut pair: SparseSetPair<i32> = SparseSetPair::new();
+        let key = pair.current_mut().push(42);
+        pair.current_mut().swap_remove(key);
+
+        assert_eq!(pair.carry_over(key), None);
+        assert_eq!(pair.current().len(), 0);
+        assert_eq!(pair.next().len(), 0);
+    }
+
+    // sparse set pair with two items in current, only one carried over, swap twice => the
+    // leftover item from the first step is gone, not resurrected in the second current
+    #[test]
+    fn sparse_set_pair_with_an_uncarried_leftover_swap_twice_leftover_does_not_resurrect() {
+        let mut pair: SparseSetPair<i32> = SparseSetPair::new();
+        let kept_key = pair.current_mut().push(1);
+        let _dropped_key = pair.current_mut().push(2);
+
+        let carried_key = pair.carry_over(kept_key).unwrap();
+        pair.swap();
+        assert_eq!(pair.current().values().copied().collect::<Vec<_>>(), [1]);
+
+        let carried_key = pair.carry_over(carried_key).unwrap();
+        pair.swap();
+
+        assert_eq!(pair.current().get(carried_key), Some(&1));
+        assert_eq!(pair.current().values().copied().collect::<Vec<_>>(), [1]);
+    }
 }
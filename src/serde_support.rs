@@ -0,0 +1,222 @@
+// Copyright (C) Pavel Grebnev 2024-2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+//! `serde` support, enabled with the `serde` feature.
+//!
+//! Serializing only the live values would break every [`SparseKey`] held outside the set once it
+//! is deserialized again, since a fresh set wouldn't know which sparse slots were already handed
+//! out, which were free, or what epoch each slot was on. Instead the serialized form captures the
+//! dense values together with the keys that reach them, the full sparse-entry table (alive slots
+//! with their epoch, free slots with their next-free link and next epoch), and the free-list
+//! head, so deserializing reproduces storage that is indistinguishable from the original: every
+//! previously-issued live key still resolves to the same value, and subsequent `push` calls reuse
+//! the same slots and epochs they would have without the round-trip.
+//!
+//! Deserializing validates the invariants a hand-built payload could violate: dense lengths must
+//! match, every dense key must point at a sparse slot that is alive with a matching epoch, every
+//! alive sparse slot must in turn be claimed by exactly one dense key (neither orphaned nor
+//! claimed twice), the free list (starting from `next_free_sparse_entry`) must visit exactly the
+//! non-alive slots other than the `retired_slot_count` retired ones, with no cycles, and every
+//! index/epoch must fit within the target
+//! `SparseSet<T, I>`'s index type `I`. Any mismatch is reported as a deserialization error rather
+//! than producing a `SparseSet` with broken invariants.
+//!
+//! Release builds don't track epochs at all (see [`crate::sparse_entry::SparseEntry`]), so every
+//! slot round-trips with epoch `0` there regardless of what it was serialized with; the epoch
+//! fields in the wire format still get validated against `I`, but they no longer affect key
+//! validity once deserialized.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::sparse_entry::SparseEntry;
+use crate::sparse_index::try_epoch;
+use crate::sparse_index::try_sparse_index;
+use crate::storage::SparseArrayStorage;
+use crate::SparseIndex;
+use crate::SparseKey;
+use crate::SparseSet;
+
+#[derive(Serialize, Deserialize)]
+enum SparseSlotRepr {
+    Alive { epoch: usize },
+    Free { next_free: usize, next_epoch: usize },
+}
+
+impl SparseSlotRepr {
+    fn from_entry<I: SparseIndex>(entry: &SparseEntry<I>) -> Self {
+        if entry.is_alive() {
+            SparseSlotRepr::Alive {
+                epoch: entry.alive_epoch(),
+            }
+        } else {
+            SparseSlotRepr::Free {
+                next_free: entry.next_free(),
+                next_epoch: entry.reused_epoch(),
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SparseSetRepr<'a, T> {
+    dense_keys: &'a [SparseKey],
+    dense_values: &'a [T],
+    sparse: Vec<SparseSlotRepr>,
+    next_free_sparse_entry: usize,
+    retired_slot_count: usize,
+    generation: usize,
+}
+
+#[derive(Deserialize)]
+struct SparseSetReprOwned<T> {
+    dense_keys: Vec<SparseKey>,
+    dense_values: Vec<T>,
+    sparse: Vec<SparseSlotRepr>,
+    next_free_sparse_entry: usize,
+    retired_slot_count: usize,
+    generation: usize,
+}
+
+impl<T: Serialize, I: SparseIndex> Serialize for SparseSet<T, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // slots at or past `frontier` are leftover capacity from before the last
+        // `clear_retaining_capacity`, not part of the set's current state
+        let sparse = self.storage.get_sparse()[..self.frontier]
+            .iter()
+            .map(SparseSlotRepr::from_entry)
+            .collect();
+
+        let repr = SparseSetRepr {
+            dense_keys: self.storage.get_dense_keys(),
+            dense_values: self.storage.get_dense_values(),
+            sparse,
+            next_free_sparse_entry: self.next_free_sparse_entry,
+            retired_slot_count: self.retired_slot_count,
+            generation: self.generation,
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, I: SparseIndex> Deserialize<'de> for SparseSet<T, I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = SparseSetReprOwned::<T>::deserialize(deserializer)?;
+
+        if repr.dense_keys.len() != repr.dense_values.len() {
+            return Err(D::Error::custom(
+                "dense_keys and dense_values must have the same length",
+            ));
+        }
+
+        let alive_slot_count = repr
+            .sparse
+            .iter()
+            .filter(|slot| matches!(slot, SparseSlotRepr::Alive { .. }))
+            .count();
+        if alive_slot_count != repr.dense_keys.len() {
+            return Err(D::Error::custom(
+                "number of alive sparse slots does not match the number of dense keys",
+            ));
+        }
+
+        if repr.next_free_sparse_entry != I::MAX_SPARSE_INDEX {
+            try_sparse_index::<I>(repr.next_free_sparse_entry).map_err(D::Error::custom)?;
+        }
+
+        let mut sparse = vec![SparseEntry::<I>::new_alive(0, 0); repr.sparse.len()];
+        for (sparse_index, slot) in repr.sparse.iter().enumerate() {
+            sparse[sparse_index] = match *slot {
+                SparseSlotRepr::Alive { epoch } => {
+                    try_epoch::<I>(epoch).map_err(D::Error::custom)?;
+                    SparseEntry::new_alive(0, epoch)
+                }
+                SparseSlotRepr::Free {
+                    next_free,
+                    next_epoch,
+                } => {
+                    if next_free != I::MAX_SPARSE_INDEX {
+                        try_sparse_index::<I>(next_free).map_err(D::Error::custom)?;
+                    }
+                    try_epoch::<I>(next_epoch).map_err(D::Error::custom)?;
+                    SparseEntry::new_free(next_free, next_epoch)
+                }
+            };
+        }
+
+        // walk the free list and make sure it visits exactly the non-alive slots that aren't
+        // retired, with no cycles and no detours through a live slot
+        let mut visited_free = vec![false; repr.sparse.len()];
+        let mut visited_free_count = 0;
+        let mut cursor = repr.next_free_sparse_entry;
+        while cursor != I::MAX_SPARSE_INDEX {
+            let Some(SparseSlotRepr::Free { next_free, .. }) = repr.sparse.get(cursor) else {
+                return Err(D::Error::custom(
+                    "free list references a live or out-of-range sparse slot",
+                ));
+            };
+            if visited_free[cursor] {
+                return Err(D::Error::custom("free list contains a cycle"));
+            }
+            visited_free[cursor] = true;
+            visited_free_count += 1;
+            cursor = *next_free;
+        }
+        if visited_free_count + repr.retired_slot_count != repr.sparse.len() - alive_slot_count {
+            return Err(D::Error::custom(
+                "free list does not cover exactly the non-alive sparse slots",
+            ));
+        }
+
+        // tracks which sparse slots have already been claimed by an earlier dense key, so a
+        // duplicated `sparse_index` (two dense keys pointing at the same slot) is caught here
+        // instead of silently overwriting whichever claim came first
+        let mut claimed = vec![false; sparse.len()];
+        for (dense_index, key) in repr.dense_keys.iter().enumerate() {
+            let sparse_index = key.sparse_index();
+            let entry = sparse
+                .get(sparse_index)
+                .ok_or_else(|| D::Error::custom("dense key refers to an out-of-range sparse slot"))?;
+            if !entry.is_alive_for(*key) {
+                return Err(D::Error::custom(
+                    "dense key does not match its sparse slot's alive state or epoch",
+                ));
+            }
+            if key.generation != repr.generation {
+                return Err(D::Error::custom(
+                    "dense key's generation does not match the set's generation",
+                ));
+            }
+            if claimed[sparse_index] {
+                return Err(D::Error::custom(
+                    "more than one dense key claims the same sparse slot",
+                ));
+            }
+            claimed[sparse_index] = true;
+            try_sparse_index::<I>(dense_index).map_err(D::Error::custom)?;
+            sparse[sparse_index].set_dense_index(dense_index);
+        }
+        if claimed
+            .iter()
+            .zip(sparse.iter())
+            .any(|(&is_claimed, entry)| entry.is_alive() && !is_claimed)
+        {
+            return Err(D::Error::custom(
+                "an alive sparse slot is not claimed by any dense key",
+            ));
+        }
+
+        let sparse_len = sparse.len();
+        Ok(SparseSet {
+            storage: SparseArrayStorage::from_parts(repr.dense_values, repr.dense_keys, sparse),
+            next_free_sparse_entry: repr.next_free_sparse_entry,
+            retired_slot_count: repr.retired_slot_count,
+            high_water_mark: 0,
+            generation: repr.generation,
+            // every deserialized slot, alive or free, already belongs to this generation, so
+            // there's no leftover prior-generation capacity for `push`/`push_front` to reclaim
+            frontier: sparse_len,
+        })
+    }
+}
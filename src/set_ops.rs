@@ -0,0 +1,225 @@
+// Copyright (C) Pavel Grebnev 2024-2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+//! Set-algebra operations that treat a [`SparseSet`]'s values as a plain set, compared by
+//! equality rather than by key.
+//!
+//! The base versions ([`SparseSet::union`] and friends) are a straightforward O(n * m) scan over
+//! the two sets' values and only need `T: PartialEq`. A faster O(n + m) path needs a `T: Hash`
+//! bound in addition, which Rust can't select between via specialization on stable, so it can't
+//! share the same method names; instead it's exposed under the `_hashed` suffix (`union_hashed`
+//! and so on) for callers whose `T` is hashable and whose sets are large enough for the
+//! temporary `HashSet` to pay for itself.
+//!
+//! Keys are per-set, so neither of the above is of any use for comparing two sets whose values
+//! don't implement `Eq`/`Hash` themselves, or where equality should be driven by some projection
+//! of the value rather than the whole value (e.g. matching entities by id while ignoring the
+//! rest of their state). The `_by_key` suffix covers that case: callers supply a `key_of`
+//! projection, and matching is driven by a `HashSet`/`HashMap` of the projected keys, scanning
+//! dense storage directly with no sparse indirection in the hot loop.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::SparseIndex;
+use crate::SparseSet;
+
+impl<T: Clone, I: SparseIndex> SparseSet<T, I> {
+    /// Like [`Self::union_hashed`], but instead of comparing values directly, compares the keys
+    /// produced by `key_of`. Two values are considered the same element if `key_of` produces
+    /// equal `K`s for them.
+    ///
+    /// `self`'s values come first in dense order, followed by `other`'s values whose key isn't
+    /// already present, in `other`'s dense order. The result is assigned brand-new keys.
+    pub fn union_by_key<K: Eq + Hash>(&self, other: &Self, key_of: impl Fn(&T) -> K) -> Self {
+        let self_keys: HashSet<K> = self.values().map(&key_of).collect();
+        let mut result: Self = self.values().cloned().collect();
+        for value in other.values() {
+            if !self_keys.contains(&key_of(value)) {
+                result.push(value.clone());
+            }
+        }
+        result
+    }
+
+    /// Like [`Self::intersection_hashed`], but instead of comparing values directly, compares
+    /// the keys produced by `key_of`. Two values are considered the same element if `key_of`
+    /// produces equal `K`s for them.
+    ///
+    /// Builds a hash index of `other`'s keys once, then scans `self`'s dense values, so it's
+    /// cheapest to call on the smaller of the two sets.
+    pub fn intersection_by_key<K: Eq + Hash>(&self, other: &Self, key_of: impl Fn(&T) -> K) -> Self {
+        let other_keys: HashSet<K> = other.values().map(&key_of).collect();
+        self.values()
+            .filter(|value| other_keys.contains(&key_of(value)))
+            .cloned()
+            .collect()
+    }
+
+    /// Like [`Self::difference_hashed`], but instead of comparing values directly, compares the
+    /// keys produced by `key_of`. Two values are considered the same element if `key_of`
+    /// produces equal `K`s for them.
+    pub fn difference_by_key<K: Eq + Hash>(&self, other: &Self, key_of: impl Fn(&T) -> K) -> Self {
+        let other_keys: HashSet<K> = other.values().map(&key_of).collect();
+        self.values()
+            .filter(|value| !other_keys.contains(&key_of(value)))
+            .cloned()
+            .collect()
+    }
+
+    /// Like [`Self::symmetric_difference_hashed`], but instead of comparing values directly,
+    /// compares the keys produced by `key_of`. Two values are considered the same element if
+    /// `key_of` produces equal `K`s for them.
+    pub fn symmetric_difference_by_key<K: Eq + Hash>(
+        &self,
+        other: &Self,
+        key_of: impl Fn(&T) -> K,
+    ) -> Self {
+        let mut result = self.difference_by_key(other, &key_of);
+        for value in other.difference_by_key(self, &key_of).values() {
+            result.push(value.clone());
+        }
+        result
+    }
+}
+
+impl<T: Clone + PartialEq, I: SparseIndex> SparseSet<T, I> {
+    /// Returns a new set containing every value from `self`, followed by every value from
+    /// `other` that doesn't already appear in `self`.
+    ///
+    /// The result is assigned brand-new keys in dense iteration order; it has no relationship to
+    /// the keys of `self` or `other`.
+    ///
+    /// O(n * m) time complexity, where n and m are the two sets' lengths.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result: Self = self.values().cloned().collect();
+        for value in other.values() {
+            if !self.values().any(|v| v == value) {
+                result.push(value.clone());
+            }
+        }
+        result
+    }
+
+    /// Returns a new set containing only the values present in both `self` and `other`.
+    ///
+    /// O(n * m) time complexity.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.values()
+            .filter(|value| other.values().any(|v| v == *value))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a new set containing the values present in `self` but not in `other`.
+    ///
+    /// O(n * m) time complexity.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.values()
+            .filter(|value| !other.values().any(|v| v == *value))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a new set containing the values present in exactly one of `self` and `other`.
+    ///
+    /// O(n * m) time complexity.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        let mut result = self.difference(other);
+        for value in other.difference(self).values() {
+            result.push(value.clone());
+        }
+        result
+    }
+}
+
+impl<T: PartialEq, I: SparseIndex> SparseSet<T, I> {
+    /// Returns true if `self` and `other` share no equal values.
+    ///
+    /// O(n * m) time complexity.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        !self.values().any(|value| other.values().any(|v| v == value))
+    }
+
+    /// Returns true if every value in `self` is also present in `other`.
+    ///
+    /// O(n * m) time complexity.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.values().all(|value| other.values().any(|v| v == value))
+    }
+
+    /// Returns true if `self` contains every value in `other`.
+    ///
+    /// O(n * m) time complexity.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+}
+
+impl<T: Clone + Eq + Hash, I: SparseIndex> SparseSet<T, I> {
+    /// Like [`Self::union`], but builds a temporary `HashSet` of `self`'s values for O(n + m)
+    /// instead of O(n * m) time complexity.
+    ///
+    /// The result is assigned brand-new keys; `self`'s values come first in dense order,
+    /// followed by `other`'s values that aren't already present, in `other`'s dense order.
+    pub fn union_hashed(&self, other: &Self) -> Self {
+        let self_values: HashSet<&T> = self.values().collect();
+        let mut result: Self = self.values().cloned().collect();
+        for value in other.values() {
+            if !self_values.contains(value) {
+                result.push(value.clone());
+            }
+        }
+        result
+    }
+
+    /// Like [`Self::intersection`], but builds a temporary `HashSet` of `other`'s values for
+    /// O(n + m) instead of O(n * m) time complexity.
+    pub fn intersection_hashed(&self, other: &Self) -> Self {
+        let other_values: HashSet<&T> = other.values().collect();
+        self.values()
+            .filter(|value| other_values.contains(*value))
+            .cloned()
+            .collect()
+    }
+
+    /// Like [`Self::difference`], but builds a temporary `HashSet` of `other`'s values for
+    /// O(n + m) instead of O(n * m) time complexity.
+    pub fn difference_hashed(&self, other: &Self) -> Self {
+        let other_values: HashSet<&T> = other.values().collect();
+        self.values()
+            .filter(|value| !other_values.contains(*value))
+            .cloned()
+            .collect()
+    }
+
+    /// Like [`Self::symmetric_difference`], but builds temporary `HashSet`s for O(n + m) instead
+    /// of O(n * m) time complexity.
+    pub fn symmetric_difference_hashed(&self, other: &Self) -> Self {
+        let mut result = self.difference_hashed(other);
+        for value in other.difference_hashed(self).values() {
+            result.push(value.clone());
+        }
+        result
+    }
+
+    /// Like [`Self::is_disjoint`], but builds a temporary `HashSet` of `other`'s values for
+    /// O(n + m) instead of O(n * m) time complexity.
+    pub fn is_disjoint_hashed(&self, other: &Self) -> bool {
+        let other_values: HashSet<&T> = other.values().collect();
+        !self.values().any(|value| other_values.contains(value))
+    }
+
+    /// Like [`Self::is_subset`], but builds a temporary `HashSet` of `other`'s values for
+    /// O(n + m) instead of O(n * m) time complexity.
+    pub fn is_subset_hashed(&self, other: &Self) -> bool {
+        let other_values: HashSet<&T> = other.values().collect();
+        self.values().all(|value| other_values.contains(value))
+    }
+
+    /// Like [`Self::is_superset`], but builds a temporary `HashSet` of `self`'s values for
+    /// O(n + m) instead of O(n * m) time complexity.
+    pub fn is_superset_hashed(&self, other: &Self) -> bool {
+        other.is_subset_hashed(self)
+    }
+}
@@ -1,6 +1,21 @@
-// Copyright (C) Pavel Grebnev 2024
+// Copyright (C) Pavel Grebnev 2024-2025
 // Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
 
+#[cfg(debug_assertions)]
+use crate::sparse_index::to_epoch;
+use crate::sparse_index::to_sparse_index;
+use crate::sparse_index::SparseIndex;
+use crate::sparse_key::SparseKey;
+
+/// Encodes a free-list pointer (a sparse index with the dead bit set) as `I`. The pointed-to
+/// index itself is validated against `MAX_SPARSE_INDEX`; the dead bit is then folded in, which
+/// deliberately pushes the encoded value above `MAX_SPARSE_INDEX` to mark it as a free entry.
+fn to_free_pointer<I: SparseIndex>(next_free: usize) -> I {
+    let _ = to_sparse_index::<I>(next_free);
+    I::try_from_usize(next_free | I::DEAD_BIT)
+        .expect("already validated next_free against MAX_SPARSE_INDEX")
+}
+
 /// A sparse entry in the sparse set.
 /// Depending on the state of the entry, the fields have different meanings:
 /// - If the entry is alive:
@@ -12,72 +27,151 @@
 ///
 /// The index in the free entry has the upper bit set to 1, that upper bit is used to
 /// differentiate between alive and free entries.
+///
+/// Both fields are stored as `I` rather than `usize`, so a `SparseSet<T, I>` with a narrower `I`
+/// (e.g. the default `u32`) keeps this entry, and thus the whole sparse array, smaller.
+///
+/// `epoch_or_next_epoch` only exists in debug builds. Release builds don't pay for it at all
+/// (one fewer `I` per slot) and skip the generational compare on every lookup entirely, trusting
+/// the caller not to hold onto a key past its slot being reused — exactly like Bevy's
+/// `ComponentSparseSet`, which keeps the same trade-off for its own generation field.
 #[derive(Copy, Clone)]
-pub(crate) struct SparseEntry {
+pub(crate) struct SparseEntry<I> {
     /// alive: dense_index, free: next_free
-    dense_index_or_next_free: usize,
+    dense_index_or_next_free: I,
     /// alive: epoch, free: next_epoch
-    epoch_or_next_epoch: usize,
+    #[cfg(debug_assertions)]
+    epoch_or_next_epoch: I,
 }
 
-const DEAD_BIT: usize = 1 << (size_of::<usize>() * 8 - 1);
-
-// The max possible value next_free can have due to the dead bit being always unset
-pub(crate) const MAX_SPARSE_INDEX: usize = DEAD_BIT - 1;
-
-pub(crate) const MAX_EPOCH: usize = usize::MAX;
-
-impl SparseEntry {
+impl<I: SparseIndex> SparseEntry<I> {
     pub(crate) fn new_alive(dense_index: usize, epoch: usize) -> Self {
+        #[cfg(not(debug_assertions))]
+        let _ = epoch;
         Self {
-            dense_index_or_next_free: dense_index,
-            epoch_or_next_epoch: epoch,
+            dense_index_or_next_free: to_sparse_index(dense_index),
+            #[cfg(debug_assertions)]
+            epoch_or_next_epoch: to_epoch(epoch),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn new_free(next_free: usize, next_epoch: usize) -> Self {
+        #[cfg(not(debug_assertions))]
+        let _ = next_epoch;
+        Self {
+            dense_index_or_next_free: to_free_pointer(next_free),
+            #[cfg(debug_assertions)]
+            epoch_or_next_epoch: to_epoch(next_epoch),
         }
     }
 
     pub(crate) fn mark_free(&mut self, next_free: usize) {
         debug_assert!(self.is_alive());
-        self.dense_index_or_next_free = next_free | DEAD_BIT;
-        self.epoch_or_next_epoch = usize::wrapping_add(self.epoch_or_next_epoch, 1);
+        self.dense_index_or_next_free = to_free_pointer(next_free);
+
+        #[cfg(debug_assertions)]
+        {
+            let current_epoch = self.epoch_or_next_epoch.to_usize();
+            let next_epoch = if current_epoch >= I::MAX_EPOCH {
+                0
+            } else {
+                current_epoch + 1
+            };
+            self.epoch_or_next_epoch = to_epoch(next_epoch);
+        }
     }
 
     pub(crate) fn replace_pointed_to_value(&mut self, new_dense_index: usize) {
         debug_assert!(self.is_alive());
-        self.dense_index_or_next_free = new_dense_index;
+        self.dense_index_or_next_free = to_sparse_index(new_dense_index);
     }
 
     pub(crate) fn is_alive(&self) -> bool {
         // use the dead bit to differentiate between alive and free entries
-        self.dense_index_or_next_free & DEAD_BIT == 0
+        self.dense_index_or_next_free.to_usize() & I::DEAD_BIT == 0
+    }
+
+    /// Whether this entry is alive and, in debug builds, still on the epoch `key` was issued
+    /// for. Release builds skip the epoch compare entirely (see the struct docs) and only check
+    /// `is_alive`, so a stale key from a reused slot is indistinguishable from a fresh one there.
+    pub(crate) fn is_alive_for(&self, key: SparseKey) -> bool {
+        if !self.is_alive() {
+            return false;
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = key;
+        #[cfg(debug_assertions)]
+        {
+            self.epoch() == key.epoch
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            true
+        }
     }
 
     pub(crate) fn dense_index(&self) -> usize {
         debug_assert!(self.is_alive());
-        self.dense_index_or_next_free
+        self.dense_index_or_next_free.to_usize()
     }
 
+    #[cfg(debug_assertions)]
     pub(crate) fn epoch(&self) -> usize {
         debug_assert!(self.is_alive());
-        self.epoch_or_next_epoch
+        self.epoch_or_next_epoch.to_usize()
+    }
+
+    /// This alive entry's epoch, for carrying it over to a new `SparseEntry` built from the same
+    /// slot (e.g. when the dense index it points at moves). Always `0` in release builds, which
+    /// is harmless since nothing ever reads it back through [`Self::is_alive_for`] there.
+    pub(crate) fn alive_epoch(&self) -> usize {
+        #[cfg(debug_assertions)]
+        {
+            self.epoch()
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            0
+        }
     }
 
     pub(crate) fn next_free(&self) -> usize {
         debug_assert!(!self.is_alive());
-        self.dense_index_or_next_free & !DEAD_BIT
+        self.dense_index_or_next_free.to_usize() & !I::DEAD_BIT
     }
 
+    pub(crate) fn set_next_free(&mut self, next_free: usize) {
+        debug_assert!(!self.is_alive());
+        self.dense_index_or_next_free = to_free_pointer(next_free);
+    }
+
+    #[cfg(debug_assertions)]
     pub(crate) fn next_epoch(&self) -> usize {
         debug_assert!(!self.is_alive());
-        self.epoch_or_next_epoch
+        self.epoch_or_next_epoch.to_usize()
+    }
+
+    /// The epoch a key should carry when this free entry's slot gets reused by [`Self::new_alive`].
+    /// Always `0` in release builds, for the same reason as [`Self::alive_epoch`].
+    pub(crate) fn reused_epoch(&self) -> usize {
+        #[cfg(debug_assertions)]
+        {
+            self.next_epoch()
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            0
+        }
     }
 
     pub(crate) fn set_dense_index(&mut self, dense_index: usize) {
         debug_assert!(self.is_alive());
-        self.dense_index_or_next_free = dense_index;
+        self.dense_index_or_next_free = to_sparse_index(dense_index);
     }
 
     pub(crate) fn dense_index_move_left(&mut self) {
         debug_assert!(self.is_alive());
-        self.dense_index_or_next_free -= 1;
+        self.dense_index_or_next_free = to_sparse_index(self.dense_index() - 1);
     }
 }
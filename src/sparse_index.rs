@@ -0,0 +1,92 @@
+// Copyright (C) Pavel Grebnev 2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+//! The integer primitive [`SparseSet`](crate::SparseSet) uses internally for its sparse-entry
+//! bookkeeping (dense index, free-list links, and epoch counters), selectable per set via its `I`
+//! type parameter.
+//!
+//! Just like [`crate::sparse_entry::SparseEntry`] itself, one bit of `I` is reserved to tell
+//! alive slots from free ones apart, so the largest sparse index a given `I` can actually hold
+//! ([`SparseIndex::MAX_SPARSE_INDEX`]) is a little short of a plain `I::MAX`. The epoch field
+//! carries no such bit, so [`SparseIndex::MAX_EPOCH`] is the full `I::MAX`.
+
+use std::fmt::Debug;
+
+/// An integer primitive [`crate::SparseSet`] can use for its sparse-entry bookkeeping.
+///
+/// Implemented for `u32` (the default, matching Bevy-style entity indices), `u64`, and `usize`.
+/// Picking a narrower `I` shrinks the sparse array's per-slot footprint for collections that will
+/// never need the full range of indices/epochs `usize` allows.
+pub trait SparseIndex: Copy + Eq + Debug + 'static {
+    /// The bit reserved to distinguish alive slots from free ones, in `usize` terms.
+    const DEAD_BIT: usize;
+    /// The largest sparse index `Self` can represent once `DEAD_BIT` is reserved; also doubles
+    /// as the "no next free slot" sentinel.
+    const MAX_SPARSE_INDEX: usize;
+    /// The largest epoch `Self` can represent.
+    const MAX_EPOCH: usize;
+
+    fn to_usize(self) -> usize;
+    fn try_from_usize(value: usize) -> Option<Self>;
+}
+
+macro_rules! impl_sparse_index {
+    ($ty:ty, $dead_bit:expr) => {
+        impl SparseIndex for $ty {
+            const DEAD_BIT: usize = $dead_bit;
+            const MAX_SPARSE_INDEX: usize = Self::DEAD_BIT - 1;
+            const MAX_EPOCH: usize = <$ty>::MAX as usize;
+
+            fn to_usize(self) -> usize {
+                self as usize
+            }
+
+            fn try_from_usize(value: usize) -> Option<Self> {
+                <$ty>::try_from(value).ok()
+            }
+        }
+    };
+}
+
+impl_sparse_index!(u32, 1 << 31);
+impl_sparse_index!(u64, 1 << 63);
+impl_sparse_index!(usize, 1 << (usize::BITS - 1));
+
+/// Converts a dense index/free-list link into `I`, panicking if it exceeds
+/// [`SparseIndex::MAX_SPARSE_INDEX`].
+pub(crate) fn to_sparse_index<I: SparseIndex>(value: usize) -> I {
+    try_sparse_index(value).unwrap_or_else(|message| panic!("{message}"))
+}
+
+/// Like [`to_sparse_index`], but reports the overflow as an `Err` instead of panicking, for
+/// callers (like `serde` deserialization) that need to surface it as a regular error.
+pub(crate) fn try_sparse_index<I: SparseIndex>(value: usize) -> Result<I, String> {
+    if value > I::MAX_SPARSE_INDEX {
+        return Err(format!(
+            "sparse index {value} exceeds the maximum this SparseSet's index type can represent ({})",
+            I::MAX_SPARSE_INDEX
+        ));
+    }
+    Ok(I::try_from_usize(value).expect("already checked against MAX_SPARSE_INDEX"))
+}
+
+/// Converts an epoch into `I`, panicking if it exceeds [`SparseIndex::MAX_EPOCH`].
+///
+/// Only used in debug builds: that's the only place [`crate::sparse_entry::SparseEntry`] tracks
+/// an epoch at all (see its docs), so this has no caller left in release builds.
+#[cfg(debug_assertions)]
+pub(crate) fn to_epoch<I: SparseIndex>(value: usize) -> I {
+    try_epoch(value).unwrap_or_else(|message| panic!("{message}"))
+}
+
+/// Like [`to_epoch`], but reports the overflow as an `Err` instead of panicking, for callers
+/// (like `serde` deserialization) that need to surface it as a regular error.
+pub(crate) fn try_epoch<I: SparseIndex>(value: usize) -> Result<I, String> {
+    if value > I::MAX_EPOCH {
+        return Err(format!(
+            "epoch {value} exceeds the maximum this SparseSet's index type can represent ({})",
+            I::MAX_EPOCH
+        ));
+    }
+    Ok(I::try_from_usize(value).expect("already checked against MAX_EPOCH"))
+}
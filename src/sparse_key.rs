@@ -0,0 +1,123 @@
+// Copyright (C) Pavel Grebnev 2024-2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+use std::num::NonZeroUsize;
+
+use crate::sparse_index::SparseIndex;
+
+// a SparseKey's sparse_index always stores the real usize position in the sparse array,
+// regardless of which index type `I` a given SparseSet<T, I> uses internally for its entries; it
+// just needs to be able to hold the largest index any of our SparseIndex impls can ever produce
+// (their own DEAD_BIT already rules out the top half of their range), which is well within the
+// single value NonMaxUsize reserves as its niche
+const _: () = assert!(u32::MAX_SPARSE_INDEX < NonMaxUsize::MAX);
+const _: () = assert!(u64::MAX_SPARSE_INDEX < NonMaxUsize::MAX);
+const _: () = assert!(usize::MAX_SPARSE_INDEX < NonMaxUsize::MAX);
+
+/// A `usize` that can hold every value except `usize::MAX`.
+///
+/// The forbidden value is reserved as a niche (stored as `NonZeroUsize` of `!value`), so
+/// `Option<NonMaxUsize>` takes no more space than a plain `usize`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct NonMaxUsize(NonZeroUsize);
+
+impl NonMaxUsize {
+    /// The largest value a `NonMaxUsize` can represent.
+    pub(crate) const MAX: usize = usize::MAX - 1;
+
+    pub(crate) fn new(value: usize) -> Option<Self> {
+        NonZeroUsize::new(!value).map(Self)
+    }
+
+    pub(crate) fn get(self) -> usize {
+        !self.0.get()
+    }
+}
+
+impl std::fmt::Debug for NonMaxUsize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+/// A stable handle to a value stored in a [`crate::SparseSet`].
+///
+/// A key stays valid for as long as the element it points to hasn't been removed, even across
+/// insertions or operations that change the order of elements (like `swap_remove`). Once the
+/// element is removed, the key becomes invalid; in debug builds it stays invalid even if another
+/// element later ends up reusing the same underlying slot, while release builds skip the epoch
+/// check and resolve it to that new element.
+///
+/// `generation` ties a key to the particular "epoch" of the whole set it was issued in: every
+/// [`crate::SparseSet::clear_retaining_capacity`] call bumps the set's own generation counter,
+/// which instantly invalidates every key issued before it, in O(1), without touching a single
+/// sparse slot. `epoch` is the unrelated, finer-grained per-slot counter guarding a single
+/// slot's remove/reuse cycle *within* one generation.
+///
+/// `sparse_index` uses a niche-optimized representation, so `Option<SparseKey>` is the same size
+/// as `SparseKey` itself.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SparseKey {
+    pub(crate) sparse_index: NonMaxUsize,
+    pub(crate) epoch: usize,
+    pub(crate) generation: usize,
+}
+
+impl SparseKey {
+    pub(crate) fn new(sparse_index: usize, epoch: usize, generation: usize) -> Self {
+        Self {
+            sparse_index: NonMaxUsize::new(sparse_index)
+                .expect("sparse_index must not exceed MAX_SPARSE_INDEX"),
+            epoch,
+            generation,
+        }
+    }
+
+    pub(crate) fn sparse_index(&self) -> usize {
+        self.sparse_index.get()
+    }
+}
+
+impl std::fmt::Debug for SparseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SparseKey")
+            .field("sparse_index", &self.sparse_index())
+            .field("epoch", &self.epoch)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SparseKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("SparseKey", 3)?;
+        state.serialize_field("sparse_index", &self.sparse_index())?;
+        state.serialize_field("epoch", &self.epoch)?;
+        state.serialize_field("generation", &self.generation)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SparseKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct SparseKeyRepr {
+            sparse_index: usize,
+            epoch: usize,
+            generation: usize,
+        }
+
+        let repr = SparseKeyRepr::deserialize(deserializer)?;
+        let sparse_index = NonMaxUsize::new(repr.sparse_index)
+            .ok_or_else(|| serde::de::Error::custom("sparse_index must not be usize::MAX"))?;
+        Ok(SparseKey {
+            sparse_index,
+            epoch: repr.epoch,
+            generation: repr.generation,
+        })
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (C) Pavel Grebnev 2024-2025
+// Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
+
+use crate::SparseIndex;
+use crate::SparseKey;
+use crate::SparseSet;
+
+/// A pair of [`SparseSet`]s for step-to-step workflows that compute a new "active" set from the
+/// previous one and then swap roles, the way regex-automata drives an NFA simulation with a pair
+/// of sparse sets: `current` holds this step's elements, `next` accumulates the ones that should
+/// carry on into the following step, and [`Self::swap`] exchanges the two in O(1) once a step is
+/// done.
+///
+/// [`Self::carry_over`] is what makes this more than two plain `SparseSet`s glued together: since
+/// a key is only ever valid against the set it was issued from, moving a value from `current` to
+/// `next` on its own would hand back an unrelated key, leaving the caller to track which set each
+/// of their keys currently belongs to. The key `carry_over` returns is valid against
+/// [`Self::next`] and, once [`Self::swap`] runs, against the new [`Self::current`]; carrying the
+/// element over again invalidates it and returns a new key in its place.
+#[derive(Clone)]
+pub struct SparseSetPair<T, I: SparseIndex = u32> {
+    current: SparseSet<T, I>,
+    next: SparseSet<T, I>,
+}
+
+impl<T, I: SparseIndex> SparseSetPair<T, I> {
+    /// Creates a new, empty pair. Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            current: SparseSet::new(),
+            next: SparseSet::new(),
+        }
+    }
+
+    /// Creates a new, empty pair with allocated memory for the given number of elements in each
+    /// of `current` and `next`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the type `T` is zero-sized.
+    /// - Panics if the memory allocation fails.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            current: SparseSet::with_capacity(capacity),
+            next: SparseSet::with_capacity(capacity),
+        }
+    }
+
+    /// The set being read this step.
+    pub fn current(&self) -> &SparseSet<T, I> {
+        &self.current
+    }
+
+    /// The set being read this step, mutably.
+    pub fn current_mut(&mut self) -> &mut SparseSet<T, I> {
+        &mut self.current
+    }
+
+    /// The set being built for the next step.
+    pub fn next(&self) -> &SparseSet<T, I> {
+        &self.next
+    }
+
+    /// The set being built for the next step, mutably.
+    pub fn next_mut(&mut self) -> &mut SparseSet<T, I> {
+        &mut self.next
+    }
+
+    /// Exchanges `current` and `next`, so whatever was carried over during this step becomes the
+    /// new `current`, and clears the new `next` so the following step starts from an empty
+    /// scratch set.
+    ///
+    /// Without the clear, whatever was left behind in the old `current` (everything that wasn't
+    /// carried over) would still be sitting in the new `next` and would resurface the step after
+    /// next, defeating the point of a scratch/current double buffer. Clearing costs O(1) (see
+    /// [`SparseSet::clear_retaining_capacity`]), so the whole operation stays O(1).
+    pub fn swap(&mut self) {
+        std::mem::swap(&mut self.current, &mut self.next);
+        self.next.clear_retaining_capacity();
+    }
+
+    /// Moves the element `key` points at from `current` into `next`, returning the key it's
+    /// reachable by there (which becomes valid against [`Self::current`] after the following
+    /// [`Self::swap`]).
+    ///
+    /// Returns `None`, without modifying either set, if `key` isn't alive in `current`.
+    ///
+    /// Removal from `current` is order-disturbing (see [`SparseSet::swap_remove`]), which is fine
+    /// here: by the time a step is done, `current` is about to be swapped out entirely, so
+    /// nothing depends on the order of what's left behind in it.
+    pub fn carry_over(&mut self, key: SparseKey) -> Option<SparseKey> {
+        let value = self.current.swap_remove(key)?;
+        Some(self.next.push(value))
+    }
+}
+
+impl<T, I: SparseIndex> Default for SparseSetPair<T, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
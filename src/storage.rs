@@ -2,6 +2,7 @@
 // Distributed under the MIT License (license terms are at http://opensource.org/licenses/MIT).
 
 use crate::sparse_entry;
+use crate::sparse_index::SparseIndex;
 use crate::sparse_key;
 
 use sparse_entry::SparseEntry;
@@ -9,7 +10,7 @@ use sparse_key::SparseKey;
 
 /// SparseArrayStorage is a storage for sparse set, it is a combination of dense and sparse arrays.
 /// Dense array stores values, sparse array stores keys to the dense array.
-pub(crate) struct SparseArrayStorage<T> {
+pub(crate) struct SparseArrayStorage<T, I: SparseIndex> {
     // pointer to the start of the dense values array
     dense_values_start_ptr: *mut T,
     // pointer to the dense keys array
@@ -17,7 +18,7 @@ pub(crate) struct SparseArrayStorage<T> {
     // amount of used elements in the dense array
     dense_len: usize,
     // pointer to the sparse array
-    sparse_start_ptr: *mut SparseEntry,
+    sparse_start_ptr: *mut SparseEntry<I>,
     // amount of used elements in the sparse array
     sparse_len: usize,
 
@@ -31,7 +32,7 @@ pub(crate) struct SparseArrayStorage<T> {
     layout: Option<std::alloc::Layout>,
 }
 
-impl<T> SparseArrayStorage<T> {
+impl<T, I: SparseIndex> SparseArrayStorage<T, I> {
     // don't waste space for big objects, and for smaller ones don't waste time on early reallocations
     const MIN_NON_ZERO_CAPACITY: usize = if size_of::<T>() <= 1024 { 4 } else { 1 };
 
@@ -63,7 +64,7 @@ impl<T> SparseArrayStorage<T> {
             dense_values_start_ptr: buffer as *mut T,
             dense_keys_start_ptr: unsafe { buffer.add(dense_keys_offset) as *mut SparseKey },
             dense_len: 0,
-            sparse_start_ptr: unsafe { buffer.add(sparse_offset) as *mut SparseEntry },
+            sparse_start_ptr: unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> },
             sparse_len: 0,
 
             max_dense_elements: capacity,
@@ -79,13 +80,15 @@ impl<T> SparseArrayStorage<T> {
     /// - Providing position out of bounds of alive keys/values can lead to UB
     /// - Calling this function when there are free sparse entries left
     /// can lead to an inconsistent state of the storage that can later lead to UB
-    pub(crate) fn insert_with_new_sparse_item(&mut self, position: usize, value: T) -> SparseKey {
+    pub(crate) fn insert_with_new_sparse_item(
+        &mut self,
+        position: usize,
+        value: T,
+        generation: usize,
+    ) -> SparseKey {
         let old_sparse_len = self.sparse_len;
 
-        let key = SparseKey {
-            sparse_index: old_sparse_len,
-            epoch: 0,
-        };
+        let key = SparseKey::new(old_sparse_len, 0, generation);
         let new_sparse_entry = SparseEntry::new_alive(position, 0);
 
         if self.sparse_len == self.max_sparse_elements {
@@ -136,7 +139,7 @@ impl<T> SparseArrayStorage<T> {
         self.dense_len += 1;
 
         unsafe {
-            let sparse_entry = self.sparse_start_ptr.add(key.sparse_index);
+            let sparse_entry = self.sparse_start_ptr.add(key.sparse_index());
             *sparse_entry = SparseEntry::new_alive(position, key.epoch);
         }
     }
@@ -220,6 +223,27 @@ impl<T> SparseArrayStorage<T> {
         }
     }
 
+    /// Drops every dense element at index >= `new_len` and shrinks the dense length to it.
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must be less than or equal to the current dense length.
+    pub(crate) fn truncate_dense(&mut self, new_len: usize) {
+        if new_len >= self.dense_len {
+            return;
+        }
+
+        if std::mem::needs_drop::<T>() {
+            for i in new_len..self.dense_len {
+                unsafe {
+                    std::ptr::drop_in_place(self.dense_values_start_ptr.add(i));
+                }
+            }
+        }
+
+        self.dense_len = new_len;
+    }
+
     pub(crate) fn into_dense_values(mut self) -> Vec<T> {
         // we are going to drop the set, so make sure we don't drop the values again
         // after we moved them out
@@ -252,11 +276,11 @@ impl<T> SparseArrayStorage<T> {
         unsafe { std::slice::from_raw_parts_mut(self.dense_keys_start_ptr, self.dense_len) }
     }
 
-    pub(crate) fn get_sparse(&self) -> &[SparseEntry] {
+    pub(crate) fn get_sparse(&self) -> &[SparseEntry<I>] {
         unsafe { std::slice::from_raw_parts(self.sparse_start_ptr, self.sparse_len) }
     }
 
-    pub(crate) fn get_sparse_mut(&mut self) -> &mut [SparseEntry] {
+    pub(crate) fn get_sparse_mut(&mut self) -> &mut [SparseEntry<I>] {
         unsafe { std::slice::from_raw_parts_mut(self.sparse_start_ptr, self.sparse_len) }
     }
 
@@ -268,10 +292,30 @@ impl<T> SparseArrayStorage<T> {
         self.max_dense_elements
     }
 
+    pub(crate) fn get_sparse_capacity(&self) -> usize {
+        self.max_sparse_elements
+    }
+
     pub(crate) fn get_sparse_len(&self) -> usize {
         self.sparse_len
     }
 
+    /// Shrinks the sparse length to `new_len`, discarding the trailing entries.
+    ///
+    /// A no-op if `new_len` is already greater than or equal to the current sparse length.
+    ///
+    /// # Safety
+    ///
+    /// Every discarded entry (at index >= `new_len`) must not be alive, and must not be
+    /// referenced by the free list, or callers will be left holding a dangling link/key.
+    pub(crate) fn truncate_sparse(&mut self, new_len: usize) {
+        if new_len >= self.sparse_len {
+            return;
+        }
+
+        self.sparse_len = new_len;
+    }
+
     pub(crate) fn reserve(&mut self, additional: usize) {
         if additional == 0 {
             return;
@@ -305,7 +349,7 @@ impl<T> SparseArrayStorage<T> {
                 let new_dense_values_start_ptr = buffer as *mut T;
                 let new_dense_keys_start_ptr =
                     unsafe { buffer.add(dense_keys_offset) as *mut SparseKey };
-                let new_sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry };
+                let new_sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> };
 
                 // copy the old values
                 unsafe {
@@ -353,7 +397,7 @@ impl<T> SparseArrayStorage<T> {
             self.dense_values_start_ptr = buffer as *mut T;
             self.dense_keys_start_ptr = unsafe { buffer.add(dense_keys_offset) as *mut SparseKey };
             self.dense_len = 0;
-            self.sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry };
+            self.sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> };
             self.sparse_len = 0;
 
             self.max_dense_elements = desired_capacity;
@@ -364,6 +408,141 @@ impl<T> SparseArrayStorage<T> {
         }
     }
 
+    /// Shrinks the allocation so that it holds at most `max(min_capacity, sparse_len)` sparse
+    /// slots. A no-op if the buffer is already that small or smaller, or if nothing was ever
+    /// allocated.
+    ///
+    /// Note that `sparse_len` (not `dense_len`) is the floor: sparse slots belonging to removed
+    /// elements can't be dropped without renumbering the slots that come after them, which would
+    /// invalidate outstanding keys, so only the unused tail of the allocation can be released.
+    pub(crate) fn shrink_to(&mut self, min_capacity: usize) {
+        let Some(previous_layout) = self.layout else {
+            return;
+        };
+
+        let new_max_sparse_elements = min_capacity.max(self.sparse_len);
+        if new_max_sparse_elements >= self.max_sparse_elements {
+            return;
+        }
+
+        if new_max_sparse_elements == 0 {
+            Self::deallocate_buffer(self.buffer, previous_layout);
+
+            self.dense_values_start_ptr = std::ptr::NonNull::dangling().as_ptr();
+            self.dense_keys_start_ptr = std::ptr::NonNull::dangling().as_ptr();
+            self.sparse_start_ptr = std::ptr::NonNull::dangling().as_ptr();
+
+            self.max_dense_elements = 0;
+            self.max_sparse_elements = 0;
+
+            self.buffer = std::ptr::null_mut();
+            self.layout = None;
+            return;
+        }
+
+        // free sparse slots can be reused by a later push without growing the buffer, so the
+        // dense arrays keep room for every sparse slot
+        let new_max_dense_elements = new_max_sparse_elements;
+
+        let (layout, buffer, dense_keys_offset, sparse_offset) = Self::allocate_new_buffer(
+            size_of::<T>(),
+            align_of::<T>(),
+            new_max_dense_elements,
+            new_max_sparse_elements,
+        );
+
+        let new_dense_values_start_ptr = buffer as *mut T;
+        let new_dense_keys_start_ptr = unsafe { buffer.add(dense_keys_offset) as *mut SparseKey };
+        let new_sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                self.dense_values_start_ptr,
+                new_dense_values_start_ptr,
+                self.dense_len,
+            );
+            std::ptr::copy_nonoverlapping(
+                self.dense_keys_start_ptr,
+                new_dense_keys_start_ptr,
+                self.dense_len,
+            );
+            std::ptr::copy_nonoverlapping(
+                self.sparse_start_ptr,
+                new_sparse_start_ptr,
+                self.sparse_len,
+            );
+        }
+
+        Self::deallocate_buffer(self.buffer, previous_layout);
+
+        self.dense_values_start_ptr = new_dense_values_start_ptr;
+        self.dense_keys_start_ptr = new_dense_keys_start_ptr;
+        self.sparse_start_ptr = new_sparse_start_ptr;
+
+        self.max_dense_elements = new_max_dense_elements;
+        self.max_sparse_elements = new_max_sparse_elements;
+
+        self.buffer = buffer;
+        self.layout = layout;
+    }
+
+    /// Rebuilds storage directly from already-assembled dense and sparse arrays, bypassing the
+    /// normal insertion bookkeeping. Used when deserializing, where the caller has already
+    /// reconstructed the exact sparse layout (including free-list links and epochs) that existed
+    /// before serialization.
+    ///
+    /// # Safety
+    ///
+    /// `dense_values.len()` must equal `dense_keys.len()`, and every alive `SparseEntry` in
+    /// `sparse` must have a `dense_index()` that is a valid, distinct index into `dense_values`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_parts(
+        mut dense_values: Vec<T>,
+        mut dense_keys: Vec<SparseKey>,
+        mut sparse: Vec<SparseEntry<I>>,
+    ) -> Self {
+        let dense_len = dense_values.len();
+        let sparse_len = sparse.len();
+
+        if sparse_len == 0 {
+            return Self::new();
+        }
+
+        // the dense arrays get room for every sparse slot, since a later push can reuse a free
+        // slot without growing the buffer
+        let (layout, buffer, dense_keys_offset, sparse_offset) =
+            Self::allocate_new_buffer(size_of::<T>(), align_of::<T>(), sparse_len, sparse_len);
+
+        let dense_values_start_ptr = buffer as *mut T;
+        let dense_keys_start_ptr = unsafe { buffer.add(dense_keys_offset) as *mut SparseKey };
+        let sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(dense_values.as_ptr(), dense_values_start_ptr, dense_len);
+            std::ptr::copy_nonoverlapping(dense_keys.as_ptr(), dense_keys_start_ptr, dense_len);
+            std::ptr::copy_nonoverlapping(sparse.as_ptr(), sparse_start_ptr, sparse_len);
+
+            // the three buffers above now own the data; forget it here so it isn't dropped twice
+            dense_values.set_len(0);
+            dense_keys.set_len(0);
+            sparse.set_len(0);
+        }
+
+        Self {
+            dense_values_start_ptr,
+            dense_keys_start_ptr,
+            dense_len,
+            sparse_start_ptr,
+            sparse_len,
+
+            max_dense_elements: sparse_len,
+            max_sparse_elements: sparse_len,
+
+            buffer,
+            layout,
+        }
+    }
+
     fn shift_dense_values_to_the_right(
         &mut self,
         start_index: usize,
@@ -390,18 +569,18 @@ impl<T> SparseArrayStorage<T> {
         new_max_dense_values: usize,
         new_max_sparse_values: usize,
     ) -> (Option<std::alloc::Layout>, *mut u8, usize, usize) {
-        const SIZE_OF_DENSE_KEY: usize = size_of::<SparseKey>();
-        const SIZE_OF_SPARSE_ENTRY: usize = size_of::<SparseEntry>();
+        let size_of_sparse_entry = size_of::<SparseEntry<I>>();
+        let align_of_sparse_entry = align_of::<SparseEntry<I>>();
 
+        const SIZE_OF_DENSE_KEY: usize = size_of::<SparseKey>();
         const ALIGN_OF_DENSE_KEY: usize = align_of::<SparseKey>();
-        const ALIGN_OF_SPARSE_ENTRY: usize = align_of::<SparseEntry>();
 
         // for the simplicity sake, we take the largest alignment
         // we could theoretically go with the alignment of the first element,
         // but that would require calculating the paddings based on runtime value of the pointer
         let align_of_buffer: usize = align_of_value
             .max(ALIGN_OF_DENSE_KEY)
-            .max(ALIGN_OF_SPARSE_ENTRY);
+            .max(align_of_sparse_entry);
         let values_end = size_of_value * new_max_dense_values;
 
         let value_size_reminder = values_end % ALIGN_OF_DENSE_KEY;
@@ -410,12 +589,12 @@ impl<T> SparseArrayStorage<T> {
 
         let dense_keys_end = dense_keys_offset + SIZE_OF_DENSE_KEY * new_max_dense_values;
 
-        let dense_keys_size_reminder = dense_keys_end % ALIGN_OF_SPARSE_ENTRY;
+        let dense_keys_size_reminder = dense_keys_end % align_of_sparse_entry;
         let sparse_offset = dense_keys_end
-            + (ALIGN_OF_SPARSE_ENTRY - dense_keys_size_reminder)
+            + (align_of_sparse_entry - dense_keys_size_reminder)
                 * (dense_keys_size_reminder != 0) as usize;
 
-        let sparse_end = sparse_offset + SIZE_OF_SPARSE_ENTRY * new_max_sparse_values;
+        let sparse_end = sparse_offset + size_of_sparse_entry * new_max_sparse_values;
         let buffer_size_reminder = sparse_end % align_of_buffer;
         // the buffer size should be a multiple of the alignment
         let size_of_buffer = sparse_end
@@ -444,7 +623,7 @@ impl<T> SparseArrayStorage<T> {
     }
 }
 
-impl<T> Clone for SparseArrayStorage<T>
+impl<T, I: SparseIndex> Clone for SparseArrayStorage<T, I>
 where
     T: Clone,
 {
@@ -458,7 +637,7 @@ where
 
         let new_dense_values_start_ptr = buffer as *mut T;
         let new_dense_keys_start_ptr = unsafe { buffer.add(dense_keys_offset) as *mut SparseKey };
-        let new_sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry };
+        let new_sparse_start_ptr = unsafe { buffer.add(sparse_offset) as *mut SparseEntry<I> };
 
         unsafe {
             // copy by invoking clone on the elements that don't have Copy trait
@@ -505,7 +684,7 @@ where
     }
 }
 
-impl<T> Drop for SparseArrayStorage<T> {
+impl<T, I: SparseIndex> Drop for SparseArrayStorage<T, I> {
     fn drop(&mut self) {
         if let Some(layout) = self.layout {
             self.clear_dense();
@@ -514,5 +693,5 @@ impl<T> Drop for SparseArrayStorage<T> {
     }
 }
 
-unsafe impl<T: Send> Send for SparseArrayStorage<T> {}
-unsafe impl<T: Sync> Sync for SparseArrayStorage<T> {}
+unsafe impl<T: Send, I: Send + SparseIndex> Send for SparseArrayStorage<T, I> {}
+unsafe impl<T: Sync, I: Sync + SparseIndex> Sync for SparseArrayStorage<T, I> {}